@@ -0,0 +1,62 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rscheme::Interpreter;
+
+const PROGRAMS: &str = "
+(define (fib n)
+  (if (< n 2)
+      n
+      (+ (fib (- n 1)) (fib (- n 2)))))
+
+(define (tak x y z)
+  (if (not (< y x))
+      z
+      (tak (tak (- x 1) y z)
+           (tak (- y 1) z x)
+           (tak (- z 1) x y))))
+
+(define (queens n)
+  (define (iota1 n)
+    (let loop ((i n) (l '()))
+      (if (= i 0) l (loop (- i 1) (cons i l)))))
+  (define (try x y z)
+    (if (null? x)
+        (if (null? y) 1 0)
+        (+ (if (ok? (car x) 1 z)
+               (try (append (cdr x) y) '() (cons (car x) z))
+               0)
+           (try (cdr x) (cons (car x) y) z))))
+  (define (ok? row dist placed)
+    (if (null? placed)
+        #t
+        (and (not (= (car placed) (+ row dist)))
+             (not (= (car placed) (- row dist)))
+             (ok? row (+ dist 1) (cdr placed)))))
+  (try (iota1 n) '() '()))
+";
+
+const BENCHMARKS: [(&str, &str, &str); 3] = [
+    ("fib", "(fib 20)", "6765"),
+    ("tak", "(tak 18 12 6)", "7"),
+    ("nqueens", "(queens 8)", "92"),
+];
+
+/// Runs each benchmark with the tree-walking evaluator and with the bytecode virtual machine.
+fn benchmarks(c: &mut Criterion) {
+    let eval = Interpreter::new();
+    eval.eval_str(PROGRAMS).unwrap();
+    let vm = Interpreter::new();
+    vm.run_str(PROGRAMS).unwrap();
+
+    for (name, exp, expected) in BENCHMARKS {
+        assert_eq!(eval.eval_str(exp).unwrap().to_string(), expected);
+        assert_eq!(vm.run_str(exp).unwrap().to_string(), expected);
+        let mut group = c.benchmark_group(name);
+        group.sample_size(10);
+        group.bench_function("eval", |b| b.iter(|| eval.eval_str(exp).unwrap()));
+        group.bench_function("vm", |b| b.iter(|| vm.run_str(exp).unwrap()));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,122 @@
+use std::cmp::Ordering;
+
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::strings::char_arg;
+use crate::types::Arity;
+use crate::types::Value;
+
+/// Binds the character procedures in `env`.
+pub fn add_char_procedures(env: &Environment) {
+    env.define_primitive("char?", Arity::Exactly(1), |args| {
+        Ok(Value::Boolean(matches!(args[0], Value::Char(_))))
+    });
+    env.define_primitive("char->integer", Arity::Exactly(1), |args| {
+        Ok(Value::Integer(char_arg("char->integer", &args[0])? as i64))
+    });
+    env.define_primitive("integer->char", Arity::Exactly(1), |args| {
+        let c = match args[0] {
+            Value::Integer(n) => u32::try_from(n).ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match c {
+            Some(c) => Ok(Value::Char(c)),
+            None => Err(Error::wrong_type(
+                "integer->char",
+                "Unicode scalar value",
+                &args[0],
+            )),
+        }
+    });
+    env.define_primitive(
+        "char-upcase",
+        Arity::Exactly(1),
+        map_char("char-upcase", |c| c.to_uppercase().next().unwrap_or(c)),
+    );
+    env.define_primitive(
+        "char-downcase",
+        Arity::Exactly(1),
+        map_char("char-downcase", |c| c.to_lowercase().next().unwrap_or(c)),
+    );
+    env.define_primitive(
+        "char-alphabetic?",
+        Arity::Exactly(1),
+        test_char("char-alphabetic?", char::is_alphabetic),
+    );
+    env.define_primitive(
+        "char-numeric?",
+        Arity::Exactly(1),
+        test_char("char-numeric?", |c| c.is_ascii_digit()),
+    );
+    env.define_primitive(
+        "char-whitespace?",
+        Arity::Exactly(1),
+        test_char("char-whitespace?", char::is_whitespace),
+    );
+    env.define_primitive(
+        "char-upper-case?",
+        Arity::Exactly(1),
+        test_char("char-upper-case?", char::is_uppercase),
+    );
+    env.define_primitive(
+        "char-lower-case?",
+        Arity::Exactly(1),
+        test_char("char-lower-case?", char::is_lowercase),
+    );
+    env.define_primitive(
+        "char=?",
+        Arity::AtLeast(1),
+        compare_chars("char=?", |o| o == Ordering::Equal),
+    );
+    env.define_primitive(
+        "char<?",
+        Arity::AtLeast(1),
+        compare_chars("char<?", |o| o == Ordering::Less),
+    );
+    env.define_primitive(
+        "char>?",
+        Arity::AtLeast(1),
+        compare_chars("char>?", |o| o == Ordering::Greater),
+    );
+    env.define_primitive(
+        "char<=?",
+        Arity::AtLeast(1),
+        compare_chars("char<=?", |o| o != Ordering::Greater),
+    );
+    env.define_primitive(
+        "char>=?",
+        Arity::AtLeast(1),
+        compare_chars("char>=?", |o| o != Ordering::Less),
+    );
+}
+
+fn map_char(
+    name: &'static str,
+    f: fn(char) -> char,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| Ok(Value::Char(f(char_arg(name, &args[0])?)))
+}
+
+fn test_char(
+    name: &'static str,
+    test: fn(char) -> bool,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| Ok(Value::Boolean(test(char_arg(name, &args[0])?)))
+}
+
+/// Builds a comparison that holds when `holds` accepts the ordering of every pair of adjacent
+/// arguments.
+fn compare_chars(
+    name: &'static str,
+    holds: fn(Ordering) -> bool,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        let chars = args
+            .iter()
+            .map(|arg| char_arg(name, arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::Boolean(
+            chars.windows(2).all(|pair| holds(pair[0].cmp(&pair[1]))),
+        ))
+    }
+}
@@ -0,0 +1,1049 @@
+//! Compiles parsed expressions to bytecode for the virtual machine in [`crate::vm`].
+//!
+//! Variables bound by `lambda`, `let` and internal definitions are resolved at compile time to
+//! the number of frames to walk up and a slot in that frame. Every other variable is global and
+//! looked up by name. The compiler only handles the core special forms. Anything else, such as a
+//! macro use or a malformed form, makes it give up on the whole top-level expression, which is
+//! then evaluated by [`crate::eval`] instead.
+
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::error::Span;
+use crate::eval::{check_distinct, unwrap_form};
+use crate::reader::Datum;
+use crate::types::{Arity, Symbol, Value};
+
+/// A bytecode instruction. Operands index the tables of the [`Code`] the instruction is part of,
+/// or are positions in its instructions for jumps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    /// Pushes a constant.
+    Const(u32),
+    /// Pushes a fresh copy of a quoted list, vector or bytevector, so that mutating it doesn't
+    /// change what later evaluations of the literal give.
+    Quote(u32),
+    /// Pushes a local variable, given as a frame depth and a slot.
+    Local(u32, u32),
+    /// Pushes a local variable that might not be assigned yet, which is an error named by the
+    /// third operand.
+    CheckedLocal(u32, u32, u32),
+    /// Assigns the value on top of the stack to a local variable, leaving it there.
+    SetLocal(u32, u32),
+    /// Pushes the value of the named global variable.
+    Global(u32),
+    /// Assigns the value on top of the stack to an existing global variable, leaving it there.
+    SetGlobal(u32),
+    /// Binds the value on top of the stack to a global variable, leaving it there.
+    DefineGlobal(u32),
+    Pop,
+    Jump(u32),
+    /// Pops the value on top of the stack and jumps if it is `#f`.
+    JumpIfFalse(u32),
+    /// Jumps if the value on top of the stack is `#f`, and pops it otherwise.
+    JumpIfFalseOrPop(u32),
+    /// Jumps if the value on top of the stack is not `#f`, and pops it otherwise.
+    JumpIfTrueOrPop(u32),
+    /// Pops the value on top of the stack and jumps unless it is `eqv?` to an element of the
+    /// constant list given by the first operand.
+    JumpUnlessMemv(u32, u32),
+    /// Pops a value and the one below it, and pushes a pair of them, the first as the car.
+    Cons,
+    /// Pops a list and the value below it, and pushes a copy of the list ending in that value.
+    Splice,
+    /// Replaces the list on top of the stack with a vector of its elements.
+    ListToVector,
+    /// Pushes a procedure made of a nested code object, closing over the current frame.
+    Closure(u32),
+    /// Calls the procedure below the given number of arguments, replacing them with the result.
+    Call(u32),
+    /// Calls a procedure like `Call`, then returns its result.
+    TailCall(u32),
+    /// Returns the value on top of the stack.
+    Return,
+}
+
+/// The compiled body of a procedure, or of a top-level expression.
+#[derive(Debug)]
+pub struct Code {
+    pub name: Option<String>,
+    /// The number of required parameters.
+    pub params: usize,
+    /// Whether the arguments after the required ones are passed as a list in the next slot.
+    pub rest: bool,
+    /// The number of slots in a frame of this code, for its parameters and the variables bound
+    /// in its body.
+    pub slots: usize,
+    pub ops: Vec<Op>,
+    /// The innermost list each instruction was compiled from, to locate errors.
+    pub spans: Vec<Option<Span>>,
+    pub constants: Vec<Value>,
+    /// The literals copied by `Quote`.
+    pub literals: Vec<Datum>,
+    /// The names of the global variables and of the locals that can be referenced unassigned.
+    pub names: Vec<String>,
+    /// The code of the procedures created by `Closure`.
+    pub codes: Vec<Rc<Code>>,
+}
+
+impl Code {
+    pub fn arity(&self) -> Arity {
+        if self.rest {
+            Arity::AtLeast(self.params)
+        } else {
+            Arity::Exactly(self.params)
+        }
+    }
+
+    /// The name the procedure was defined with, for error messages.
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("#<procedure>")
+    }
+}
+
+/// Compiles a top-level expression to code taking no arguments. Returns `None` if the
+/// expression uses something the compiler doesn't support, including macros bound in `globals`.
+pub fn compile(exp: &Datum, globals: &Environment) -> Option<Rc<Code>> {
+    let mut compiler = Compiler {
+        scopes: Vec::new(),
+        globals,
+        defining: None,
+    };
+    compiler.scopes.push(Scope::new(None, 0, false, true));
+    compiler.compile_body(0, std::slice::from_ref(exp), true)?;
+    compiler.emit(Op::Return);
+    Some(Rc::new(compiler.scopes.pop().unwrap().code))
+}
+
+/// A variable visible in a frame.
+struct Local {
+    name: String,
+    slot: u32,
+    /// Whether the variable can be referenced before it's assigned, like an internal definition.
+    checked: bool,
+}
+
+/// The code being compiled for one frame, and the variables in scope in it.
+struct Scope {
+    code: Code,
+    locals: Vec<Local>,
+    /// Whether definitions in the body are global rather than local to the frame.
+    top_level: bool,
+    span: Option<Span>,
+}
+
+impl Scope {
+    fn new(name: Option<String>, params: usize, rest: bool, top_level: bool) -> Self {
+        Scope {
+            code: Code {
+                name,
+                params,
+                rest,
+                slots: 0,
+                ops: Vec::new(),
+                spans: Vec::new(),
+                constants: Vec::new(),
+                literals: Vec::new(),
+                names: Vec::new(),
+                codes: Vec::new(),
+            },
+            locals: Vec::new(),
+            top_level,
+            span: None,
+        }
+    }
+}
+
+struct Compiler<'a> {
+    scopes: Vec<Scope>,
+    globals: &'a Environment,
+    /// The global variable a top-level definition is binding, which its value may already call.
+    defining: Option<String>,
+}
+
+/// Where a variable reference resolves to.
+enum Variable {
+    Local {
+        depth: u32,
+        slot: u32,
+        checked: bool,
+    },
+    Global,
+}
+
+impl Compiler<'_> {
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        let scope = self.scope();
+        scope.code.ops.push(op);
+        scope.code.spans.push(scope.span.clone());
+        scope.code.ops.len() - 1
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.scope().code.ops.len() as u32;
+        match &mut self.scope().code.ops[at] {
+            Op::Jump(to)
+            | Op::JumpIfFalse(to)
+            | Op::JumpIfFalseOrPop(to)
+            | Op::JumpIfTrueOrPop(to)
+            | Op::JumpUnlessMemv(_, to) => *to = target,
+            _ => unreachable!(),
+        }
+    }
+
+    fn constant(&mut self, value: Value) {
+        let index = self.constant_index(value);
+        self.emit(Op::Const(index));
+    }
+
+    fn constant_index(&mut self, value: Value) -> u32 {
+        let constants = &mut self.scope().code.constants;
+        constants.push(value);
+        constants.len() as u32 - 1
+    }
+
+    /// Pushes the value of a quoted or self-evaluating datum.
+    fn literal(&mut self, datum: &Datum) {
+        match datum {
+            Datum::List(items, _) if items.is_empty() => self.constant(Value::Null),
+            Datum::List(_, _)
+            | Datum::DottedList(_, _)
+            | Datum::Vector(_)
+            | Datum::Bytevector(_) => {
+                let literals = &mut self.scope().code.literals;
+                literals.push(datum.clone());
+                let index = literals.len() as u32 - 1;
+                self.emit(Op::Quote(index));
+            }
+            _ => self.constant(datum.to_value()),
+        }
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        let names = &mut self.scope().code.names;
+        match names.iter().position(|n| n == name) {
+            Some(index) => index as u32,
+            None => {
+                names.push(name.to_string());
+                names.len() as u32 - 1
+            }
+        }
+    }
+
+    /// Adds a variable to the innermost frame, in scope until the frame's locals are truncated.
+    fn bind(&mut self, name: &str, checked: bool) -> u32 {
+        let slot = self.slot();
+        self.scope().locals.push(Local {
+            name: name.to_string(),
+            slot,
+            checked,
+        });
+        slot
+    }
+
+    /// Allocates a slot in the innermost frame that no variable refers to, for values the
+    /// compiled code keeps for itself.
+    fn slot(&mut self) -> u32 {
+        let scope = self.scope();
+        scope.code.slots += 1;
+        scope.code.slots as u32 - 1
+    }
+
+    fn resolve(&self, name: &str) -> Variable {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(local) = scope.locals.iter().rev().find(|local| local.name == name) {
+                return Variable::Local {
+                    depth: depth as u32,
+                    slot: local.slot,
+                    checked: local.checked,
+                };
+            }
+        }
+        Variable::Global
+    }
+
+    fn compile(&mut self, exp: &Datum, tail: bool) -> Option<()> {
+        match exp {
+            Datum::Boolean(b) => self.constant(Value::Boolean(*b)),
+            Datum::Integer(n) => self.constant(Value::Integer(*n)),
+            Datum::Float(n) => self.constant(Value::Float(*n)),
+            Datum::String(_)
+            | Datum::Char(_)
+            | Datum::BigInteger(_)
+            | Datum::Rational(_)
+            | Datum::Vector(_)
+            | Datum::Bytevector(_) => self.literal(exp),
+            Datum::Symbol(name) => self.compile_reference(name),
+            Datum::List(items, span) => {
+                let enclosing = self.scope().span.replace(span.clone());
+                let res = self.compile_list(items, tail);
+                self.scope().span = enclosing;
+                return res;
+            }
+            Datum::Renamed(_) | Datum::DottedList(_, _) => return None,
+        }
+        Some(())
+    }
+
+    fn compile_reference(&mut self, name: &str) {
+        match self.resolve(name) {
+            Variable::Local {
+                depth,
+                slot,
+                checked: true,
+            } => {
+                let name = self.name(name);
+                self.emit(Op::CheckedLocal(depth, slot, name));
+            }
+            Variable::Local { depth, slot, .. } => {
+                self.emit(Op::Local(depth, slot));
+            }
+            Variable::Global => {
+                let name = self.name(name);
+                self.emit(Op::Global(name));
+            }
+        }
+    }
+
+    fn compile_list(&mut self, items: &[Datum], tail: bool) -> Option<()> {
+        let (head, args) = items.split_first()?;
+        // Like in eval, a variable or macro bound to the keyword shadows the special form
+        let special = match head {
+            Datum::Symbol(name) => match self.resolve(name) {
+                Variable::Local { .. } => None,
+                Variable::Global => match self.globals.get_value(name) {
+                    Some(Value::Macro(_)) => return None,
+                    Some(_) => None,
+                    None if self.defining.as_deref() == Some(name.as_str()) => None,
+                    None => Some(name),
+                },
+            },
+            _ => None,
+        };
+        if let Some(keyword) = special {
+            match keyword.as_str() {
+                "quote" => match args {
+                    [datum] => {
+                        self.literal(datum);
+                        return Some(());
+                    }
+                    _ => return None,
+                },
+                "define" => return self.compile_define(args),
+                "set!" => return self.compile_set(args),
+                "lambda" => match args {
+                    [formals, body @ ..] if !body.is_empty() => {
+                        let (params, rest) = parse_formals(formals)?;
+                        return self.compile_lambda(None, params, rest, body);
+                    }
+                    _ => return None,
+                },
+                "begin" => return self.compile_sequence(args, tail),
+                "let" => return self.compile_let(args, tail),
+                "let*" => return self.compile_let_star(args, tail),
+                "letrec" => return self.compile_letrec(false, args, tail),
+                "letrec*" => return self.compile_letrec(true, args, tail),
+                "if" => return self.compile_if(args, tail),
+                "cond" => return self.compile_cond(args, tail),
+                "and" => return self.compile_and_or(true, args, tail),
+                "or" => return self.compile_and_or(false, args, tail),
+                "when" => return self.compile_when(true, args, tail),
+                "unless" => return self.compile_when(false, args, tail),
+                "quasiquote" => match args {
+                    [template] => return self.compile_quasiquote(template, 1),
+                    _ => return None,
+                },
+                "case" => return self.compile_case(args, tail),
+                "do" => return self.compile_do(args, tail),
+                // Anything else is unbound for now, but could be defined as a macro before the
+                // call is made, so only the evaluator knows what to do with it
+                _ => return None,
+            }
+        }
+        self.compile(head, false)?;
+        for arg in args {
+            self.compile(arg, false)?;
+        }
+        let argc = args.len() as u32;
+        self.emit(if tail {
+            Op::TailCall(argc)
+        } else {
+            Op::Call(argc)
+        });
+        Some(())
+    }
+
+    /// Compiles a body whose own variables are the locals bound since `mark`. Definitions in the
+    /// body are local to it unless it is the top level, and shadow outer variables from its
+    /// start.
+    fn compile_body(&mut self, mark: usize, body: &[Datum], tail: bool) -> Option<()> {
+        if !self.scope().top_level {
+            let mut names = Vec::new();
+            definitions(body, &mut names);
+            for name in names {
+                let own = &self.scope().locals[mark..];
+                if !own.iter().any(|local| local.name == name) {
+                    self.bind(name, true);
+                }
+            }
+        }
+        self.compile_sequence(body, tail)
+    }
+
+    fn compile_sequence(&mut self, exps: &[Datum], tail: bool) -> Option<()> {
+        let (last, init) = match exps.split_last() {
+            Some(split) => split,
+            None => {
+                self.constant(Value::Null);
+                return Some(());
+            }
+        };
+        for exp in init {
+            self.compile(exp, false)?;
+            self.emit(Op::Pop);
+        }
+        self.compile(last, tail)
+    }
+
+    fn compile_define(&mut self, args: &[Datum]) -> Option<()> {
+        if self.scope().top_level {
+            let name = match args.first() {
+                Some(Datum::List(signature, _) | Datum::DottedList(signature, _)) => {
+                    signature.first()
+                }
+                name => name,
+            };
+            self.defining = name.and_then(as_identifier).map(str::to_string);
+        }
+        let name = match args {
+            [Datum::Symbol(name), exp] => {
+                self.compile(exp, false)?;
+                name
+            }
+            // (define (name params...) body...) and (define (name params... . rest) body...)
+            [Datum::List(signature, _), body @ ..] if !body.is_empty() => {
+                let (name, params) = signature.split_first()?;
+                let name = as_identifier(name)?;
+                let params = params.iter().map(as_identifier).collect::<Option<_>>()?;
+                self.compile_lambda(Some(name), params, None, body)?;
+                name
+            }
+            [Datum::DottedList(signature, rest), body @ ..] if !body.is_empty() => {
+                let (name, params) = signature.split_first()?;
+                let name = as_identifier(name)?;
+                let params = params.iter().map(as_identifier).collect::<Option<_>>()?;
+                self.compile_lambda(Some(name), params, Some(as_identifier(rest)?), body)?;
+                name
+            }
+            _ => return None,
+        };
+        if self.scope().top_level {
+            let name = self.name(name);
+            self.emit(Op::DefineGlobal(name));
+            return Some(());
+        }
+        // Definitions in the frame were bound by compile_body, anywhere else isn't supported
+        match self.resolve(name) {
+            Variable::Local { depth: 0, slot, .. } => {
+                self.emit(Op::SetLocal(0, slot));
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    fn compile_set(&mut self, args: &[Datum]) -> Option<()> {
+        let (name, exp) = match args {
+            [Datum::Symbol(name), exp] => (name, exp),
+            _ => return None,
+        };
+        self.compile(exp, false)?;
+        match self.resolve(name) {
+            Variable::Local { depth, slot, .. } => self.emit(Op::SetLocal(depth, slot)),
+            Variable::Global => {
+                let name = self.name(name);
+                self.emit(Op::SetGlobal(name))
+            }
+        };
+        Some(())
+    }
+
+    /// Compiles the body of a procedure into a nested code object, and pushes a closure of it.
+    fn compile_lambda(
+        &mut self,
+        name: Option<&str>,
+        params: Vec<&str>,
+        rest: Option<&str>,
+        body: &[Datum],
+    ) -> Option<()> {
+        self.compile_procedure(name, params, rest, |compiler| {
+            compiler.compile_body(0, body, true)
+        })
+    }
+
+    /// Compiles a procedure whose body is compiled by `compile_body`, in tail position and with
+    /// the parameters in scope. Parameter lists naming a variable twice are left to the
+    /// evaluator to report.
+    fn compile_procedure(
+        &mut self,
+        name: Option<&str>,
+        params: Vec<&str>,
+        rest: Option<&str>,
+        compile_body: impl FnOnce(&mut Self) -> Option<()>,
+    ) -> Option<()> {
+        check_distinct("lambda", params.iter().copied().chain(rest)).ok()?;
+        let mut scope = Scope::new(
+            name.map(str::to_string),
+            params.len(),
+            rest.is_some(),
+            false,
+        );
+        scope.span = self.scope().span.clone();
+        self.scopes.push(scope);
+        for param in params.into_iter().chain(rest) {
+            self.bind(param, false);
+        }
+        let res = compile_body(self);
+        self.emit(Op::Return);
+        let code = self.scopes.pop().unwrap().code;
+        res?;
+        let codes = &mut self.scope().code.codes;
+        codes.push(Rc::new(code));
+        let index = codes.len() as u32 - 1;
+        self.emit(Op::Closure(index));
+        Some(())
+    }
+
+    /// Compiles `body` with the variables bound since `mark` in scope, then takes them out of
+    /// scope again. Their slots stay allocated.
+    fn compile_scoped_body(&mut self, mark: usize, body: &[Datum], tail: bool) -> Option<()> {
+        let top_level = std::mem::replace(&mut self.scope().top_level, false);
+        let res = self.compile_body(mark, body, tail);
+        let scope = self.scope();
+        scope.top_level = top_level;
+        scope.locals.truncate(mark);
+        res
+    }
+
+    fn compile_let(&mut self, args: &[Datum], tail: bool) -> Option<()> {
+        if let [Datum::Symbol(name), bindings, body @ ..] = args {
+            return self.compile_named_let(name, bindings, body, tail);
+        }
+        let (bindings, body) = match args {
+            [bindings, body @ ..] if !body.is_empty() => (parse_bindings(bindings, false)?, body),
+            _ => return None,
+        };
+        for (_, init) in &bindings {
+            self.compile(init, false)?;
+        }
+        let mark = self.scope().locals.len();
+        let slots: Vec<u32> = bindings
+            .iter()
+            .map(|(name, _)| self.bind(name, false))
+            .collect();
+        for slot in slots.into_iter().rev() {
+            self.emit(Op::SetLocal(0, slot));
+            self.emit(Op::Pop);
+        }
+        self.compile_scoped_body(mark, body, tail)
+    }
+
+    /// `(let name ((var init) ...) body...)` stores a procedure in a slot visible only from its
+    /// own body, then calls it.
+    fn compile_named_let(
+        &mut self,
+        name: &str,
+        bindings: &Datum,
+        body: &[Datum],
+        tail: bool,
+    ) -> Option<()> {
+        let bindings = parse_bindings(bindings, false)?;
+        if body.is_empty() {
+            return None;
+        }
+        let params = bindings.iter().map(|(var, _)| *var).collect();
+        let mark = self.scope().locals.len();
+        let slot = self.bind(name, false);
+        let res = self.compile_lambda(Some(name), params, None, body);
+        self.scope().locals.truncate(mark);
+        res?;
+        self.emit(Op::SetLocal(0, slot));
+        for (_, init) in &bindings {
+            self.compile(init, false)?;
+        }
+        let argc = bindings.len() as u32;
+        self.emit(if tail {
+            Op::TailCall(argc)
+        } else {
+            Op::Call(argc)
+        });
+        Some(())
+    }
+
+    fn compile_let_star(&mut self, args: &[Datum], tail: bool) -> Option<()> {
+        let (bindings, body) = match args {
+            [bindings, body @ ..] if !body.is_empty() => (parse_bindings(bindings, true)?, body),
+            _ => return None,
+        };
+        let mark = self.scope().locals.len();
+        for (name, init) in bindings {
+            self.compile(init, false)?;
+            let slot = self.bind(name, false);
+            self.emit(Op::SetLocal(0, slot));
+            self.emit(Op::Pop);
+        }
+        self.compile_scoped_body(mark, body, tail)
+    }
+
+    /// Compiles `letrec`, or `letrec*` if `sequential` is set. `letrec*` assigns each variable as
+    /// soon as its initializer is evaluated, `letrec` only once all of them are. Referencing a
+    /// variable before it's assigned is an error.
+    fn compile_letrec(&mut self, sequential: bool, args: &[Datum], tail: bool) -> Option<()> {
+        let (bindings, body) = match args {
+            [bindings, body @ ..] if !body.is_empty() => (parse_bindings(bindings, false)?, body),
+            _ => return None,
+        };
+        let mark = self.scope().locals.len();
+        let slots: Vec<u32> = bindings
+            .iter()
+            .map(|(name, _)| self.bind(name, true))
+            .collect();
+        if sequential {
+            for ((_, init), slot) in bindings.iter().zip(slots) {
+                self.compile(init, false)?;
+                self.emit(Op::SetLocal(0, slot));
+                self.emit(Op::Pop);
+            }
+        } else {
+            // letrec evaluates every init before assigning any variable
+            for (_, init) in &bindings {
+                self.compile(init, false)?;
+            }
+            for slot in slots.into_iter().rev() {
+                self.emit(Op::SetLocal(0, slot));
+                self.emit(Op::Pop);
+            }
+        }
+        self.compile_scoped_body(mark, body, tail)
+    }
+
+    fn compile_if(&mut self, args: &[Datum], tail: bool) -> Option<()> {
+        let (test, consequent, alternative) = match args {
+            [test, consequent] => (test, consequent, None),
+            [test, consequent, alternative] => (test, consequent, Some(alternative)),
+            _ => return None,
+        };
+        self.compile(test, false)?;
+        let to_alternative = self.emit(Op::JumpIfFalse(0));
+        self.compile(consequent, tail)?;
+        let to_end = self.emit(Op::Jump(0));
+        self.patch(to_alternative);
+        match alternative {
+            Some(alternative) => self.compile(alternative, tail)?,
+            None => self.constant(Value::Null),
+        }
+        self.patch(to_end);
+        Some(())
+    }
+
+    fn compile_cond(&mut self, args: &[Datum], tail: bool) -> Option<()> {
+        if args.is_empty() {
+            return None;
+        }
+        let mut to_end = Vec::new();
+        for (i, clause) in args.iter().enumerate() {
+            let (test, body) = match clause {
+                Datum::List(clause, _) if !clause.is_empty() => (&clause[0], &clause[1..]),
+                _ => return None,
+            };
+            if test.keyword() == Some("else") {
+                if i != args.len() - 1 {
+                    return None;
+                }
+                self.compile_sequence(body, tail)?;
+                for at in to_end {
+                    self.patch(at);
+                }
+                return Some(());
+            }
+            self.compile(test, false)?;
+            if body.is_empty() {
+                to_end.push(self.emit(Op::JumpIfTrueOrPop(0)));
+                continue;
+            }
+            // The value of the test is kept for a receiver after =>
+            let val = match body[0].keyword() {
+                Some("=>") => {
+                    let val = self.slot();
+                    self.emit(Op::SetLocal(0, val));
+                    Some(val)
+                }
+                _ => None,
+            };
+            let to_next = self.emit(Op::JumpIfFalse(0));
+            self.compile_clause_body(val, body, tail)?;
+            to_end.push(self.emit(Op::Jump(0)));
+            self.patch(to_next);
+        }
+        self.constant(Value::Null);
+        for at in to_end {
+            self.patch(at);
+        }
+        Some(())
+    }
+
+    /// The key is kept in a slot, to compare it with the data of each clause and pass it to a
+    /// receiver after `=>`.
+    fn compile_case(&mut self, args: &[Datum], tail: bool) -> Option<()> {
+        let (key, clauses) = args.split_first()?;
+        self.compile(key, false)?;
+        let key = self.slot();
+        self.emit(Op::SetLocal(0, key));
+        self.emit(Op::Pop);
+        let mut to_end = Vec::new();
+        for (i, clause) in clauses.iter().enumerate() {
+            let (data, body) = match clause {
+                Datum::List(clause, _) if clause.len() >= 2 => (&clause[0], &clause[1..]),
+                _ => return None,
+            };
+            let to_next = match data {
+                Datum::List(data, _) => {
+                    let data = data.iter().rev().fold(Value::Null, |list, datum| {
+                        Value::cons(datum.to_value(), list)
+                    });
+                    let data = self.constant_index(data);
+                    self.emit(Op::Local(0, key));
+                    Some(self.emit(Op::JumpUnlessMemv(data, 0)))
+                }
+                _ if data.keyword() == Some("else") && i == clauses.len() - 1 => None,
+                _ => return None,
+            };
+            self.compile_clause_body(Some(key), body, tail)?;
+            match to_next {
+                Some(to_next) => {
+                    to_end.push(self.emit(Op::Jump(0)));
+                    self.patch(to_next);
+                }
+                None => {
+                    for at in to_end {
+                        self.patch(at);
+                    }
+                    return Some(());
+                }
+            }
+        }
+        self.constant(Value::Null);
+        for at in to_end {
+            self.patch(at);
+        }
+        Some(())
+    }
+
+    /// Compiles the body of a `cond` or `case` clause. A receiver after `=>` is called with the
+    /// value in slot `val`.
+    fn compile_clause_body(&mut self, val: Option<u32>, body: &[Datum], tail: bool) -> Option<()> {
+        match body {
+            [arrow, receiver] if arrow.keyword() == Some("=>") => {
+                self.compile(receiver, false)?;
+                self.emit(Op::Local(0, val?));
+                self.emit(if tail { Op::TailCall(1) } else { Op::Call(1) });
+                Some(())
+            }
+            [arrow, ..] if arrow.keyword() == Some("=>") => None,
+            _ => self.compile_sequence(body, tail),
+        }
+    }
+
+    /// `(do ((var init step)...) (test result...) command...)` is compiled like a named let
+    /// whose body either returns the results or runs the commands and loops with the steps.
+    /// Every iteration gets a fresh frame, so closures capture that iteration's variables.
+    fn compile_do(&mut self, args: &[Datum], tail: bool) -> Option<()> {
+        let (specs, test, results, commands) = match args {
+            [Datum::List(specs, _), Datum::List(exit, _), commands @ ..] if !exit.is_empty() => {
+                (specs, &exit[0], &exit[1..], commands)
+            }
+            _ => return None,
+        };
+        let mut vars = Vec::new();
+        for spec in specs.iter() {
+            match spec {
+                Datum::List(spec, _) => match &spec[..] {
+                    [var, init] => vars.push((as_identifier(var)?, init, None)),
+                    [var, init, step] => vars.push((as_identifier(var)?, init, Some(step))),
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        let params = vars.iter().map(|(var, _, _)| *var).collect();
+        let slot = self.slot();
+        self.compile_procedure(None, params, None, |compiler| {
+            compiler.compile(test, false)?;
+            let to_commands = compiler.emit(Op::JumpIfFalse(0));
+            compiler.compile_sequence(results, true)?;
+            let to_end = compiler.emit(Op::Jump(0));
+            compiler.patch(to_commands);
+            for command in commands {
+                compiler.compile(command, false)?;
+                compiler.emit(Op::Pop);
+            }
+            compiler.emit(Op::Local(1, slot));
+            for (i, (_, _, step)) in vars.iter().enumerate() {
+                match step {
+                    Some(step) => compiler.compile(step, false)?,
+                    None => {
+                        compiler.emit(Op::Local(0, i as u32));
+                    }
+                }
+            }
+            compiler.emit(Op::TailCall(vars.len() as u32));
+            compiler.patch(to_end);
+            Some(())
+        })?;
+        self.emit(Op::SetLocal(0, slot));
+        for (_, init, _) in &vars {
+            self.compile(init, false)?;
+        }
+        let argc = vars.len() as u32;
+        self.emit(if tail {
+            Op::TailCall(argc)
+        } else {
+            Op::Call(argc)
+        });
+        Some(())
+    }
+
+    /// Builds the value of a quasiquote template like [`crate::eval`] does, evaluating the
+    /// unquotes at `depth` 1 from right to left.
+    fn compile_quasiquote(&mut self, template: &Datum, depth: usize) -> Option<()> {
+        if !mentions_unquote(template) {
+            self.literal(template);
+            return Some(());
+        }
+        if let Some(x) = unwrap_form(template, "unquote") {
+            return match depth {
+                1 => self.compile(x, false),
+                _ => self.compile_tagged("unquote", x, depth - 1),
+            };
+        }
+        if let Some(x) = unwrap_form(template, "quasiquote") {
+            return self.compile_tagged("quasiquote", x, depth + 1);
+        }
+        if let Some(x) = unwrap_form(template, "unquote-splicing") {
+            return match depth {
+                // Splicing outside a list is an error for the evaluator to report
+                1 => None,
+                _ => self.compile_tagged("unquote-splicing", x, depth - 1),
+            };
+        }
+        match template {
+            Datum::List(items, _) => self.compile_quasiquote_list(items, None, depth),
+            Datum::DottedList(items, tail) => {
+                self.compile_quasiquote_list(items, Some(tail), depth)
+            }
+            Datum::Vector(items) => {
+                self.compile_quasiquote_list(items, None, depth)?;
+                self.emit(Op::ListToVector);
+                Some(())
+            }
+            _ => {
+                self.literal(template);
+                Some(())
+            }
+        }
+    }
+
+    fn compile_quasiquote_list(
+        &mut self,
+        items: &[Datum],
+        tail: Option<&Datum>,
+        depth: usize,
+    ) -> Option<()> {
+        // The reader turns `(a . ,b)` into `(a unquote b)`
+        let items = match items {
+            [_, .., splice, _] if depth == 1 && splice.keyword() == Some("unquote-splicing") => {
+                return None
+            }
+            [init @ .., unquote, x] if unquote.keyword() == Some("unquote") => {
+                match depth {
+                    1 => self.compile(x, false)?,
+                    _ => self.compile_tagged("unquote", x, depth - 1)?,
+                }
+                init
+            }
+            _ => {
+                match tail {
+                    Some(tail) => self.compile_quasiquote(tail, depth)?,
+                    None => self.constant(Value::Null),
+                }
+                items
+            }
+        };
+        for item in items.iter().rev() {
+            match unwrap_form(item, "unquote-splicing") {
+                Some(x) if depth == 1 => {
+                    self.compile(x, false)?;
+                    self.emit(Op::Splice);
+                }
+                Some(x) => {
+                    self.compile_tagged("unquote-splicing", x, depth - 1)?;
+                    self.emit(Op::Cons);
+                }
+                None => {
+                    self.compile_quasiquote(item, depth)?;
+                    self.emit(Op::Cons);
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Builds the list `(keyword x)`, with `x` a template at `depth`.
+    fn compile_tagged(&mut self, keyword: &str, x: &Datum, depth: usize) -> Option<()> {
+        self.constant(Value::Null);
+        self.compile_quasiquote(x, depth)?;
+        self.emit(Op::Cons);
+        self.constant(Value::Symbol(Symbol::new(keyword)));
+        self.emit(Op::Cons);
+        Some(())
+    }
+
+    /// `and` stops at the first false value, `or` at the first true one.
+    fn compile_and_or(&mut self, is_and: bool, args: &[Datum], tail: bool) -> Option<()> {
+        let (last, init) = match args.split_last() {
+            Some(split) => split,
+            None => {
+                self.constant(Value::Boolean(is_and));
+                return Some(());
+            }
+        };
+        let mut to_end = Vec::new();
+        for exp in init {
+            self.compile(exp, false)?;
+            to_end.push(self.emit(if is_and {
+                Op::JumpIfFalseOrPop(0)
+            } else {
+                Op::JumpIfTrueOrPop(0)
+            }));
+        }
+        self.compile(last, tail)?;
+        for at in to_end {
+            self.patch(at);
+        }
+        Some(())
+    }
+
+    fn compile_when(&mut self, expected: bool, args: &[Datum], tail: bool) -> Option<()> {
+        let (test, body) = match args.split_first() {
+            Some((test, body)) if !body.is_empty() => (test, body),
+            _ => return None,
+        };
+        self.compile(test, false)?;
+        let to_else = self.emit(Op::JumpIfFalse(0));
+        if expected {
+            self.compile_sequence(body, tail)?;
+        } else {
+            self.constant(Value::Null);
+        }
+        let to_end = self.emit(Op::Jump(0));
+        self.patch(to_else);
+        if expected {
+            self.constant(Value::Null);
+        } else {
+            self.compile_sequence(body, tail)?;
+        }
+        self.patch(to_end);
+        Some(())
+    }
+}
+
+/// Whether `template` contains an unquote at any depth. A quasiquote template without one is
+/// just a literal.
+fn mentions_unquote(template: &Datum) -> bool {
+    match template {
+        Datum::List(items, _) | Datum::Vector(items) => items.iter().any(mentions_unquote),
+        Datum::DottedList(items, tail) => {
+            items.iter().any(mentions_unquote) || mentions_unquote(tail)
+        }
+        _ => matches!(
+            template.keyword(),
+            Some("unquote") | Some("unquote-splicing")
+        ),
+    }
+}
+
+fn as_identifier(datum: &Datum) -> Option<&str> {
+    match datum {
+        Datum::Symbol(name) => Some(name.as_str()),
+        _ => None,
+    }
+}
+
+/// Splits the formals of a lambda into the required parameters and the rest parameter, if any.
+fn parse_formals(formals: &Datum) -> Option<(Vec<&str>, Option<&str>)> {
+    match formals {
+        Datum::List(params, _) => Some((
+            params.iter().map(as_identifier).collect::<Option<_>>()?,
+            None,
+        )),
+        Datum::DottedList(params, rest) => Some((
+            params.iter().map(as_identifier).collect::<Option<_>>()?,
+            Some(as_identifier(rest)?),
+        )),
+        Datum::Symbol(rest) => Some((Vec::new(), Some(rest.as_str()))),
+        _ => None,
+    }
+}
+
+/// Splits a `((name init) ...)` binding list into names and initializers. Unless `repeatable`,
+/// as for `let*`, bindings of the same name twice are left to the evaluator to report.
+fn parse_bindings(bindings: &Datum, repeatable: bool) -> Option<Vec<(&str, &Datum)>> {
+    let bindings: Vec<_> = match bindings {
+        Datum::List(bindings, _) => bindings
+            .iter()
+            .map(|binding| match binding {
+                Datum::List(binding, _) => match &binding[..] {
+                    [name, init] => Some((as_identifier(name)?, init)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }?;
+    if !repeatable {
+        check_distinct("let", bindings.iter().map(|(name, _)| *name)).ok()?;
+    }
+    Some(bindings)
+}
+
+/// Collects the names defined by the definitions in `body`, including those spliced in by
+/// `begin`.
+fn definitions<'a>(body: &'a [Datum], names: &mut Vec<&'a str>) {
+    for exp in body {
+        let items = match exp {
+            Datum::List(items, _) => items,
+            _ => continue,
+        };
+        match items.first().and_then(Datum::keyword) {
+            Some("define") => {
+                let name = match items.get(1) {
+                    Some(Datum::List(signature, _)) | Some(Datum::DottedList(signature, _)) => {
+                        signature.first()
+                    }
+                    name => name,
+                };
+                if let Some(name) = name.and_then(as_identifier) {
+                    names.push(name);
+                }
+            }
+            Some("begin") => definitions(&items[1..], names),
+            _ => (),
+        }
+    }
+}
@@ -1,37 +1,140 @@
-use crate::types::check_for_floats;
-use crate::types::PrimitiveProcedure;
+use crate::chars::add_char_procedures;
+use crate::error::Error;
+use crate::gc::{self, add_gc_procedures};
+use crate::lists::add_list_procedures;
+use crate::numbers::{
+    add, compare, div, exact_integer_sqrt, expt, gcd, is_even, is_exact, is_integer, is_number,
+    is_zero, lcm, modulo, mul, negate, quotient, rem, remainder, round, sign, sqrt, sub, to_exact,
+    to_f64, Rounding,
+};
+use crate::strings::add_string_procedures;
+use crate::types::Arity;
+use crate::types::Primitive;
+use crate::types::Symbol;
 use crate::types::Value;
+use crate::vectors::add_vector_procedures;
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::{collections::HashMap, rc::Rc};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 pub struct Environment {
     bindings: RefCell<HashMap<String, Value>>,
+    /// Names declared in this frame whose definitions haven't been evaluated yet. They shadow
+    /// outer bindings, but can't be referenced until they are defined.
+    unassigned: RefCell<HashSet<String>>,
     enclosing_env: Option<Rc<Environment>>,
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         Environment {
             bindings: RefCell::new(HashMap::new()),
+            unassigned: RefCell::new(HashSet::new()),
             enclosing_env: None,
         }
     }
 
-    pub fn new_with_enclosing(enclosing: Rc<Environment>) -> Self {
-        Environment {
+    pub fn new_with_enclosing(enclosing: Rc<Environment>) -> Rc<Self> {
+        let env = Rc::new(Environment {
             bindings: RefCell::new(HashMap::new()),
-            enclosing_env: Some(enclosing.clone()),
-        }
+            unassigned: RefCell::new(HashSet::new()),
+            enclosing_env: Some(enclosing),
+        });
+        gc::track(&env);
+        env
+    }
+
+    /// Makes a top-level environment, registered with the collector so that cycles through its
+    /// bindings can be freed once it is dropped.
+    pub fn new_global() -> Rc<Self> {
+        let env = Rc::new(Environment::new());
+        gc::track(&env);
+        env
     }
 
     pub fn add_value(&self, s: &str, val: Value) {
+        if !self.unassigned.borrow().is_empty() {
+            self.unassigned.borrow_mut().remove(s);
+        }
         self.bindings.borrow_mut().insert(s.to_string(), val);
     }
 
-    pub fn get_value(&self, str: &String) -> Option<Value> {
+    /// Reserves `s` in this frame for a definition that is yet to be evaluated, unless it is
+    /// already bound here. Until then, looking it up fails instead of finding an outer binding.
+    pub fn declare(&self, s: &str) {
+        if !self.bindings.borrow().contains_key(s) {
+            self.unassigned.borrow_mut().insert(s.to_string());
+        }
+    }
+
+    /// Rebinds `s` in the innermost frame that binds or declares it. Returns `false` if `s` is
+    /// unbound.
+    pub fn set_value(&self, s: &str, val: Value) -> bool {
+        if let Some(binding) = self.bindings.borrow_mut().get_mut(s) {
+            *binding = val;
+            return true;
+        }
+        if self.unassigned.borrow().contains(s) {
+            self.add_value(s, val);
+            return true;
+        }
+        match &self.enclosing_env {
+            Some(enclosing) => enclosing.set_value(s, val),
+            None => false,
+        }
+    }
+
+    /// Binds `name` to a primitive procedure implemented by `function`, which is only called with
+    /// a number of arguments `arity` accepts. `function` can capture host state, for example
+    /// through an `Rc<RefCell<_>>`.
+    pub fn define_primitive<F>(&self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(Vec<Value>) -> Result<Value, Error> + 'static,
+    {
+        let primitive = Primitive {
+            name: name.to_string(),
+            arity,
+            function: Box::new(function),
+        };
+        self.add_value(name, Value::Primitive(Rc::new(primitive)));
+    }
+
+    /// The environment this one is nested in, if any.
+    pub(crate) fn enclosing(&self) -> Option<&Rc<Environment>> {
+        self.enclosing_env.as_ref()
+    }
+
+    /// Calls `f` on every value bound in this frame. Returns `false` without doing so if the
+    /// bindings are being modified.
+    pub(crate) fn try_for_each_value(&self, mut f: impl FnMut(&Value)) -> bool {
+        match self.bindings.try_borrow() {
+            Ok(bindings) => {
+                bindings.values().for_each(&mut f);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Removes every binding of this frame, returning the values that were bound.
+    pub(crate) fn take_values(&self) -> Vec<Value> {
+        match self.bindings.try_borrow_mut() {
+            Ok(mut bindings) => bindings.drain().map(|(_, value)| value).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn get_value(&self, str: &str) -> Option<Value> {
         match self.bindings.borrow().get(str) {
             Some(value) => Some(value.clone()),
+            None if self.unassigned.borrow().contains(str) => None,
             None => match &self.enclosing_env {
                 Some(enclosing) => enclosing.get_value(str),
                 None => None,
@@ -40,257 +143,416 @@ impl Environment {
     }
 }
 
-pub fn check_primitive_procedures(
-    proc: &str,
-) -> Option<Box<dyn Fn(Vec<Value>) -> Result<Value, &'static str>>> {
-    match proc {
-        "+" => Some(Box::new(|args| match check_for_floats(&args) {
-            Ok(true) => Ok(Value::Float(
-                args.iter()
-                    .map(|v| match v {
-                        Value::Integer(n) => *n as f64,
-                        Value::Float(n) => *n,
-                        _ => unreachable!(),
-                    })
-                    .sum(),
-            )),
-            Ok(false) => Ok(Value::Integer(
-                args.iter()
-                    .map(|v| match v {
-                        Value::Integer(n) => *n,
-                        _ => unreachable!(),
-                    })
-                    .sum(),
-            )),
-            Err(_) => Err("+: Wrong argument types!"),
-        })),
-        "-" => Some(Box::new(|args| match check_for_floats(&args) {
-            Ok(true) => Ok(Value::Float({
-                let mut iter = args.iter();
-                let a = match iter.next() {
-                    Some(Value::Integer(n)) => *n as f64,
-                    Some(Value::Float(n)) => *n,
-                    _ => 0 as f64,
-                };
-                let rest: f64 = iter
-                    .map(|v| match v {
-                        Value::Integer(n) => *n as f64,
-                        Value::Float(n) => *n,
-                        _ => unreachable!(),
-                    })
-                    .sum();
-                a - rest
-            })),
-            Ok(false) => Ok(Value::Integer({
-                let mut iter = args.iter();
-                let a = match iter.next() {
-                    Some(Value::Integer(n)) => *n,
-                    _ => 0,
-                };
-                let rest: i64 = iter
-                    .map(|v| match v {
-                        Value::Integer(n) => *n,
-                        _ => unreachable!(),
-                    })
-                    .sum();
-                a - rest
-            })),
-            Err(_) => Err("-: Wrong argument types!"),
-        })),
-        "*" => Some(Box::new(|args| match check_for_floats(&args) {
-            Ok(true) => Ok(Value::Float({
-                args.iter()
-                    .map(|v| match v {
-                        Value::Float(n) => *n,
-                        Value::Integer(n) => *n as f64,
-                        _ => unreachable!(),
-                    })
-                    .reduce(|acc, x| x * acc)
-                    .unwrap_or_else(|| 1f64)
-            })),
-            Ok(false) => Ok(Value::Integer({
-                args.iter()
-                    .map(|v| match v {
-                        Value::Integer(n) => *n,
-                        _ => unreachable!(),
-                    })
-                    .reduce(|acc, x| acc * x)
-                    .unwrap_or_else(|| 1i64)
-            })),
-            Err(_) => Err("*: Wrong argument types!"),
-        })),
-        "/" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err("/: Wrong argument number for division!");
-            }
-            let (a, b) = match (&args[0], &args[1]) {
-                (Value::Integer(x), Value::Integer(y)) => (*x as f64, *y as f64),
-                (Value::Integer(x), Value::Float(y)) => (*x as f64, *y),
-                (Value::Float(x), Value::Integer(y)) => (*x, *y as f64),
-                (Value::Float(x), Value::Float(y)) => (*x, *y),
-                _ => return Err("/: Wrong argument types for division!"),
-            };
-            if b == 0f64 {
-                return Err("/: Cannot divide by 0!");
-            }
-            Ok(Value::Float(a / b))
-        })),
-        "%" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err("%: Wrong argument number!");
-            }
-            match check_for_floats(&args) {
-                Ok(true) => {
-                    let (a, b) = match (&args[0], &args[1]) {
-                        (Value::Integer(x), Value::Integer(y)) => (*x as f64, *y as f64),
-                        (Value::Integer(x), Value::Float(y)) => (*x as f64, *y),
-                        (Value::Float(x), Value::Integer(y)) => (*x, *y as f64),
-                        (Value::Float(x), Value::Float(y)) => (*x, *y),
-                        _ => unreachable!(),
-                    };
-                    if b == 0f64 {
-                        return Err("%: Cannot divide by 0");
-                    }
-                    Ok(Value::Float(a % b))
-                }
-                Ok(false) => {
-                    let (a, b) = match (&args[0], &args[1]) {
-                        (Value::Integer(x), Value::Integer(y)) => (*x, *y),
-                        _ => unreachable!(),
-                    };
-                    if b == 0 {
-                        return Err("%: Cannot divide by 0!");
-                    }
-                    Ok(Value::Integer(a % b))
-                }
-                Err(_) => Err("%: Wrong argument types!"),
-            }
-        })),
-        "=" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err("=: Wrong argument number!");
-            }
-            return Ok(Value::Boolean(args[0].eq(&args[1])));
-        })),
-        ">" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err(">: Wrong argument number!");
-            }
-            return match args[0].partial_cmp(&args[1]) {
-                Some(Ordering::Greater) => Ok(Value::Boolean(true)),
-                Some(_) => Ok(Value::Boolean(false)),
-                _ => Err(">: Can't compare these two values!"),
-            };
-        })),
-        ">=" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err(">=: Wrong argument number!");
-            }
-            return match args[0].partial_cmp(&args[1]) {
-                Some(Ordering::Greater) | Some(Ordering::Equal) => Ok(Value::Boolean(true)),
-                Some(_) => Ok(Value::Boolean(false)),
-                _ => Err(">=: Can't compare these two values!"),
-            };
-        })),
-        "<" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err("<: Wrong argument number!");
-            }
-            return match args[0].partial_cmp(&args[1]) {
-                Some(Ordering::Less) => Ok(Value::Boolean(true)),
-                Some(_) => Ok(Value::Boolean(false)),
-                _ => Err("<: Can't compare these two values!"),
-            };
-        })),
-        "<=" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err("<=: Wrong argument number!");
-            }
-            return match args[0].partial_cmp(&args[1]) {
-                Some(Ordering::Less) | Some(Ordering::Equal) => Ok(Value::Boolean(true)),
-                Some(_) => Ok(Value::Boolean(false)),
-                _ => Err("<=: Can't compare these two values!"),
-            };
-        })),
-        "not" => Some(Box::new(|args| {
-            if args.len() != 1 {
-                return Err("not: Wrong argument number!");
-            }
-            return match args[0] {
-                Value::Boolean(b) => Ok(Value::Boolean(!b)),
-                _ => Err("not: Wrong argument type!"),
-            };
-        })),
-        "and" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err("and: Wrong argument number!");
-            }
-            return match (&args[0], &args[1]) {
-                (Value::Boolean(a), Value::Boolean(b)) => Ok(Value::Boolean(*a && *b)),
-                _ => Err("and: Wrong argument types!"),
-            };
-        })),
-        "or" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err("or: Wrong argument number!");
-            }
-            return match (&args[0], &args[1]) {
-                (Value::Boolean(a), Value::Boolean(b)) => Ok(Value::Boolean(*a || *b)),
-                _ => Err("or: Wrong argument types!"),
-            };
-        })),
-        "cons" => Some(Box::new(|args| {
-            if args.len() != 2 {
-                return Err("cons: Wrong argument number!");
-            }
-            return Ok(Value::Pair(
-                Box::new(args[0].clone()),
-                Box::new(args[1].clone()),
-            ));
-        })),
-        "car" => Some(Box::new(|args| {
-            if args.len() != 1 {
-                return Err("car: Wrong argument number!");
-            }
-            return match &args[0] {
-                Value::Pair(a, _) => Ok(*a.clone()),
-                _ => Err("car: Wrong argument type!"),
-            };
-        })),
-        "cdr" => Some(Box::new(|args| {
-            if args.len() != 1 {
-                return Err("cdr: Wrong argument number!");
-            }
-            return match &args[0] {
-                Value::Pair(_, b) => Ok(*b.clone()),
-                _ => Err("cdr: Wrong argument type!"),
-            };
-        })),
-        "list" => Some(Box::new(|args| {
-            let mut list = Value::Null;
-            for arg in args.iter().rev() {
-                list = Value::Pair(Box::new(arg.clone()), Box::new(list));
-            }
-            return Ok(list);
-        })),
-        "pair?" => Some(Box::new(|args| {
-            if args.len() != 1 {
-                return Err("pair?: Wrong argument number!");
+/// Binds every built-in primitive procedure in `env`, so they can be used as values and redefined
+/// like any other binding.
+pub fn add_primitives(env: &Environment) {
+    env.define_primitive("+", Arity::AtLeast(0), |args| {
+        number_args("+", &args)?;
+        fold_numbers("+", Value::Integer(0), &args, add)
+    });
+    env.define_primitive("-", Arity::AtLeast(1), |args| {
+        number_args("-", &args)?;
+        match &args[..] {
+            // (- x) is the negation of x
+            [x] => Ok(negate(x).unwrap()),
+            [x, rest @ ..] => fold_numbers("-", x.clone(), rest, sub),
+            [] => unreachable!(),
+        }
+    });
+    env.define_primitive("*", Arity::AtLeast(0), |args| {
+        number_args("*", &args)?;
+        fold_numbers("*", Value::Integer(1), &args, mul)
+    });
+    // (/ x) is the reciprocal of x
+    env.define_primitive("/", Arity::AtLeast(1), |args| {
+        number_args("/", &args)?;
+        let (init, divisors) = match &args[..] {
+            [_] => (Value::Integer(1), &args[..]),
+            [x, rest @ ..] => (x.clone(), rest),
+            [] => unreachable!(),
+        };
+        // Dividing by an inexact zero gives an infinity or a NaN instead
+        if divisors.iter().any(|d| is_exact(d) && is_zero(d)) {
+            return Err(Error::division_by_zero("/"));
+        }
+        fold_numbers("/", init, divisors, div)
+    });
+    env.define_primitive("%", Arity::Exactly(2), |args| {
+        number_args("%", &args)?;
+        if is_zero(&args[1]) {
+            return Err(Error::division_by_zero("%"));
+        }
+        fold_numbers("%", args[0].clone(), &args[1..], rem)
+    });
+    env.define_primitive("exact?", Arity::Exactly(1), |args| {
+        number_args("exact?", &args)?;
+        Ok(Value::Boolean(is_exact(&args[0])))
+    });
+    env.define_primitive("inexact?", Arity::Exactly(1), |args| {
+        number_args("inexact?", &args)?;
+        Ok(Value::Boolean(!is_exact(&args[0])))
+    });
+    for name in ["exact->inexact", "inexact"] {
+        env.define_primitive(name, Arity::Exactly(1), move |args| {
+            number_args(name, &args)?;
+            Ok(Value::Float(to_f64(&args[0]).unwrap()))
+        });
+    }
+    for name in ["inexact->exact", "exact"] {
+        env.define_primitive(name, Arity::Exactly(1), move |args| {
+            number_args(name, &args)?;
+            to_exact(&args[0]).ok_or_else(|| Error::wrong_type(name, "finite number", &args[0]))
+        });
+    }
+    env.define_primitive(
+        "quotient",
+        Arity::Exactly(2),
+        integer_division("quotient", quotient),
+    );
+    env.define_primitive(
+        "remainder",
+        Arity::Exactly(2),
+        integer_division("remainder", remainder),
+    );
+    env.define_primitive(
+        "modulo",
+        Arity::Exactly(2),
+        integer_division("modulo", modulo),
+    );
+    env.define_primitive("abs", Arity::Exactly(1), |args| {
+        number_args("abs", &args)?;
+        match sign(&args[0]) {
+            Some(Ordering::Less) => Ok(negate(&args[0]).unwrap()),
+            _ => Ok(args[0].clone()),
+        }
+    });
+    env.define_primitive("min", Arity::AtLeast(1), extremum("min", Ordering::Less));
+    env.define_primitive("max", Arity::AtLeast(1), extremum("max", Ordering::Greater));
+    env.define_primitive("gcd", Arity::AtLeast(0), |args| {
+        integer_args("gcd", &args)?;
+        fold_numbers("gcd", Value::Integer(0), &args, gcd)
+    });
+    env.define_primitive("lcm", Arity::AtLeast(0), |args| {
+        integer_args("lcm", &args)?;
+        fold_numbers("lcm", Value::Integer(1), &args, lcm)
+    });
+    env.define_primitive("expt", Arity::Exactly(2), |args| {
+        number_args("expt", &args)?;
+        if is_exact(&args[0]) && is_zero(&args[0]) && sign(&args[1]) == Some(Ordering::Less) {
+            return Err(Error::division_by_zero("expt"));
+        }
+        expt(&args[0], &args[1]).ok_or_else(|| Error::too_large("expt"))
+    });
+    // Returns the list (s r), where s is the integer square root of the argument and r the
+    // remainder
+    env.define_primitive("exact-integer-sqrt", Arity::Exactly(1), |args| {
+        let (s, r) = match &args[0] {
+            n @ (Value::Integer(_) | Value::BigInteger(_)) if sign(n) != Some(Ordering::Less) => {
+                exact_integer_sqrt(n).unwrap()
             }
-            return match &args[0] {
-                Value::Pair(_, _) => Ok(Value::Boolean(true)),
-                _ => Ok(Value::Boolean(false)),
-            };
-        })),
-        "null?" => Some(Box::new(|args| {
-            if args.len() != 1 {
-                return Err("null?: Wrong argument number!");
+            _ => {
+                return Err(Error::wrong_type(
+                    "exact-integer-sqrt",
+                    "non-negative exact integer",
+                    &args[0],
+                ))
             }
-            match args[0] {
-                Value::Null => Ok(Value::Boolean(true)),
-                _ => Ok(Value::Boolean(false)),
+        };
+        Ok(Value::cons(s, Value::cons(r, Value::Null)))
+    });
+    env.define_primitive("sqrt", Arity::Exactly(1), |args| {
+        number_args("sqrt", &args)?;
+        if sign(&args[0]) == Some(Ordering::Less) {
+            return Err(Error::wrong_type("sqrt", "non-negative number", &args[0]));
+        }
+        Ok(sqrt(&args[0]).unwrap())
+    });
+    env.define_primitive("exp", Arity::Exactly(1), float_function("exp", f64::exp));
+    env.define_primitive("sin", Arity::Exactly(1), float_function("sin", f64::sin));
+    env.define_primitive("cos", Arity::Exactly(1), float_function("cos", f64::cos));
+    env.define_primitive("tan", Arity::Exactly(1), float_function("tan", f64::tan));
+    // (log z b) is the logarithm of z in base b
+    env.define_primitive("log", Arity::Between(1, 2), |args| {
+        number_args("log", &args)?;
+        let z = to_f64(&args[0]).unwrap();
+        match args.get(1) {
+            Some(b) => Ok(Value::Float(z.ln() / to_f64(b).unwrap().ln())),
+            None => Ok(Value::Float(z.ln())),
+        }
+    });
+    // (atan y x) is the angle of the point (x, y)
+    env.define_primitive("atan", Arity::Between(1, 2), |args| {
+        number_args("atan", &args)?;
+        let y = to_f64(&args[0]).unwrap();
+        match args.get(1) {
+            Some(x) => Ok(Value::Float(y.atan2(to_f64(x).unwrap()))),
+            None => Ok(Value::Float(y.atan())),
+        }
+    });
+    env.define_primitive(
+        "floor",
+        Arity::Exactly(1),
+        rounding("floor", Rounding::Floor),
+    );
+    env.define_primitive(
+        "ceiling",
+        Arity::Exactly(1),
+        rounding("ceiling", Rounding::Ceiling),
+    );
+    env.define_primitive(
+        "round",
+        Arity::Exactly(1),
+        rounding("round", Rounding::Round),
+    );
+    env.define_primitive(
+        "truncate",
+        Arity::Exactly(1),
+        rounding("truncate", Rounding::Truncate),
+    );
+    env.define_primitive("number?", Arity::Exactly(1), |args| {
+        Ok(Value::Boolean(is_number(&args[0])))
+    });
+    env.define_primitive("integer?", Arity::Exactly(1), |args| {
+        Ok(Value::Boolean(is_integer(&args[0])))
+    });
+    env.define_primitive(
+        "zero?",
+        Arity::Exactly(1),
+        sign_test("zero?", |sign| sign == Ordering::Equal),
+    );
+    env.define_primitive(
+        "positive?",
+        Arity::Exactly(1),
+        sign_test("positive?", |sign| sign == Ordering::Greater),
+    );
+    env.define_primitive(
+        "negative?",
+        Arity::Exactly(1),
+        sign_test("negative?", |sign| sign == Ordering::Less),
+    );
+    env.define_primitive("odd?", Arity::Exactly(1), |args| {
+        integer_args("odd?", &args)?;
+        Ok(Value::Boolean(!is_even(&args[0]).unwrap()))
+    });
+    env.define_primitive("even?", Arity::Exactly(1), |args| {
+        integer_args("even?", &args)?;
+        Ok(Value::Boolean(is_even(&args[0]).unwrap()))
+    });
+    env.define_primitive(
+        "=",
+        Arity::AtLeast(1),
+        compare_numbers("=", |o| o == Ordering::Equal),
+    );
+    env.define_primitive(
+        "<",
+        Arity::AtLeast(1),
+        compare_numbers("<", |o| o == Ordering::Less),
+    );
+    env.define_primitive(
+        ">",
+        Arity::AtLeast(1),
+        compare_numbers(">", |o| o == Ordering::Greater),
+    );
+    env.define_primitive(
+        "<=",
+        Arity::AtLeast(1),
+        compare_numbers("<=", |o| o != Ordering::Greater),
+    );
+    env.define_primitive(
+        ">=",
+        Arity::AtLeast(1),
+        compare_numbers(">=", |o| o != Ordering::Less),
+    );
+    env.define_primitive("not", Arity::Exactly(1), |args| {
+        Ok(Value::Boolean(!args[0].is_true()))
+    });
+    env.define_primitive("cons", Arity::Exactly(2), |args| {
+        Ok(Value::cons(args[0].clone(), args[1].clone()))
+    });
+    env.define_primitive("car", Arity::Exactly(1), |args| match &args[0] {
+        Value::Pair(pair) => Ok(pair.car()),
+        _ => Err(Error::wrong_type("car", "pair", &args[0])),
+    });
+    env.define_primitive("cdr", Arity::Exactly(1), |args| match &args[0] {
+        Value::Pair(pair) => Ok(pair.cdr()),
+        _ => Err(Error::wrong_type("cdr", "pair", &args[0])),
+    });
+    env.define_primitive("set-car!", Arity::Exactly(2), |args| match &args[0] {
+        Value::Pair(pair) => {
+            *pair.car.borrow_mut() = args[1].clone();
+            Ok(Value::Null)
+        }
+        _ => Err(Error::wrong_type("set-car!", "pair", &args[0])),
+    });
+    env.define_primitive("set-cdr!", Arity::Exactly(2), |args| match &args[0] {
+        Value::Pair(pair) => {
+            *pair.cdr.borrow_mut() = args[1].clone();
+            Ok(Value::Null)
+        }
+        _ => Err(Error::wrong_type("set-cdr!", "pair", &args[0])),
+    });
+    env.define_primitive("list", Arity::AtLeast(0), |args| {
+        let mut list = Value::Null;
+        for arg in args.iter().rev() {
+            list = Value::cons(arg.clone(), list);
+        }
+        Ok(list)
+    });
+    env.define_primitive("pair?", Arity::Exactly(1), |args| match &args[0] {
+        Value::Pair(_) => Ok(Value::Boolean(true)),
+        _ => Ok(Value::Boolean(false)),
+    });
+    env.define_primitive("null?", Arity::Exactly(1), |args| match args[0] {
+        Value::Null => Ok(Value::Boolean(true)),
+        _ => Ok(Value::Boolean(false)),
+    });
+    env.define_primitive("eq?", Arity::Exactly(2), |args| {
+        Ok(Value::Boolean(args[0].is_eq(&args[1])))
+    });
+    env.define_primitive("eqv?", Arity::Exactly(2), |args| {
+        Ok(Value::Boolean(args[0].is_eqv(&args[1])))
+    });
+    env.define_primitive("equal?", Arity::Exactly(2), |args| {
+        Ok(Value::Boolean(args[0].is_equal(&args[1])))
+    });
+    env.define_primitive("procedure?", Arity::Exactly(1), |args| {
+        Ok(Value::Boolean(matches!(
+            args[0],
+            Value::Procedure(_) | Value::CaseLambda(_) | Value::Primitive(_) | Value::Closure(_)
+        )))
+    });
+    env.define_primitive("symbol?", Arity::Exactly(1), |args| {
+        Ok(Value::Boolean(matches!(args[0], Value::Symbol(_))))
+    });
+    env.define_primitive("symbol->string", Arity::Exactly(1), |args| match &args[0] {
+        Value::Symbol(s) => Ok(Value::String(s.to_string())),
+        _ => Err(Error::wrong_type("symbol->string", "symbol", &args[0])),
+    });
+    env.define_primitive("string->symbol", Arity::Exactly(1), |args| match &args[0] {
+        Value::String(s) => Ok(Value::Symbol(Symbol::new(s))),
+        _ => Err(Error::wrong_type("string->symbol", "string", &args[0])),
+    });
+    env.define_primitive("error", Arity::AtLeast(1), |args| {
+        let message = match &args[0] {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        Err(Error::User {
+            message,
+            irritants: args[1..].to_vec(),
+            span: None,
+        })
+    });
+    add_string_procedures(env);
+    add_char_procedures(env);
+    add_vector_procedures(env);
+    add_list_procedures(env);
+    add_gc_procedures(env);
+}
+
+fn number_type_error(proc: &str, args: &[Value]) -> Error {
+    let value = args.iter().find(|v| !is_number(v)).unwrap_or(&Value::Null);
+    Error::wrong_type(proc, "number", value)
+}
+
+/// Fails unless every argument is a number.
+fn number_args(proc: &str, args: &[Value]) -> Result<(), Error> {
+    match args.iter().all(is_number) {
+        true => Ok(()),
+        false => Err(number_type_error(proc, args)),
+    }
+}
+
+/// Combines `init` with each of `args` in turn, from left to right.
+fn fold_numbers(
+    proc: &str,
+    init: Value,
+    args: &[Value],
+    op: fn(&Value, &Value) -> Option<Value>,
+) -> Result<Value, Error> {
+    args.iter().try_fold(init, |acc, arg| {
+        op(&acc, arg).ok_or_else(|| number_type_error(proc, args))
+    })
+}
+
+/// Fails unless every argument is an integer, exact or not.
+fn integer_args(proc: &str, args: &[Value]) -> Result<(), Error> {
+    match args.iter().find(|v| !is_integer(v)) {
+        Some(value) => Err(Error::wrong_type(proc, "integer", value)),
+        None => Ok(()),
+    }
+}
+
+/// Builds `quotient`, `remainder` or `modulo` from the operation on two integers.
+fn integer_division(
+    name: &'static str,
+    op: fn(&Value, &Value) -> Option<Value>,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        integer_args(name, &args)?;
+        if is_zero(&args[1]) {
+            return Err(Error::division_by_zero(name));
+        }
+        Ok(op(&args[0], &args[1]).unwrap())
+    }
+}
+
+/// Builds a numeric comparison that holds when `holds` accepts the ordering of every pair of
+/// adjacent arguments. Comparing stops at the first pair for which it doesn't.
+fn compare_numbers(
+    name: &'static str,
+    holds: fn(Ordering) -> bool,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        number_args(name, &args)?;
+        Ok(Value::Boolean(args.windows(2).all(|pair| {
+            compare(&pair[0], &pair[1]).is_some_and(holds)
+        })))
+    }
+}
+
+/// Builds `min` or `max`, which return the argument that compares as `wanted` against all the
+/// others. The result is inexact if any argument is.
+fn extremum(name: &'static str, wanted: Ordering) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        number_args(name, &args)?;
+        let mut res = args[0].clone();
+        for arg in &args[1..] {
+            if compare(arg, &res) == Some(wanted) {
+                res = arg.clone();
             }
-        })),
-        _ => None,
+        }
+        if args.iter().all(is_exact) {
+            Ok(res)
+        } else {
+            Ok(Value::Float(to_f64(&res).unwrap()))
+        }
+    }
+}
+
+fn float_function(
+    name: &'static str,
+    f: fn(f64) -> f64,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        number_args(name, &args)?;
+        Ok(Value::Float(f(to_f64(&args[0]).unwrap())))
+    }
+}
+
+fn rounding(name: &'static str, mode: Rounding) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        number_args(name, &args)?;
+        Ok(round(&args[0], mode).unwrap())
+    }
+}
+
+fn sign_test(
+    name: &'static str,
+    test: fn(Ordering) -> bool,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        number_args(name, &args)?;
+        Ok(Value::Boolean(sign(&args[0]).is_some_and(test)))
     }
 }
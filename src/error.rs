@@ -0,0 +1,213 @@
+use core::fmt;
+use std::rc::Rc;
+
+use crate::types::{Arity, Value};
+
+/// A position in Scheme source code. Lines and columns start at 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub file: Option<Rc<str>>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file, self.line, self.column),
+            None => write!(f, "{}:{}", self.line, self.column),
+        }
+    }
+}
+
+/// Everything that can go wrong while reading or evaluating Scheme code.
+#[derive(Clone, Debug)]
+pub enum Error {
+    /// Malformed source text.
+    Read {
+        message: String,
+        span: Span,
+    },
+    /// A special form used with the wrong shape, e.g. `(if)`.
+    Syntax {
+        keyword: String,
+        message: String,
+        span: Option<Span>,
+    },
+    UnboundVariable {
+        name: String,
+        span: Option<Span>,
+    },
+    Arity {
+        procedure: String,
+        expected: Arity,
+        given: usize,
+        span: Option<Span>,
+    },
+    Type {
+        procedure: String,
+        expected: &'static str,
+        value: Value,
+        span: Option<Span>,
+    },
+    DivisionByZero {
+        procedure: String,
+        span: Option<Span>,
+    },
+    /// A result too large to compute, like an exact power with millions of digits.
+    TooLarge {
+        procedure: String,
+        span: Option<Span>,
+    },
+    /// Non-tail calls nested deeper than the evaluator allows.
+    RecursionTooDeep {
+        span: Option<Span>,
+    },
+    Io {
+        path: String,
+        message: String,
+    },
+    /// An error raised by Scheme code through `error`.
+    User {
+        message: String,
+        irritants: Vec<Value>,
+        span: Option<Span>,
+    },
+}
+
+impl Error {
+    pub fn syntax(keyword: &str, message: &str) -> Self {
+        Error::Syntax {
+            keyword: keyword.to_string(),
+            message: message.to_string(),
+            span: None,
+        }
+    }
+
+    pub fn unbound(name: &str) -> Self {
+        Error::UnboundVariable {
+            name: name.to_string(),
+            span: None,
+        }
+    }
+
+    pub fn arity(procedure: &str, expected: Arity, given: usize) -> Self {
+        Error::Arity {
+            procedure: procedure.to_string(),
+            expected,
+            given,
+            span: None,
+        }
+    }
+
+    pub fn wrong_type(procedure: &str, expected: &'static str, value: &Value) -> Self {
+        Error::Type {
+            procedure: procedure.to_string(),
+            expected,
+            value: value.clone(),
+            span: None,
+        }
+    }
+
+    pub fn division_by_zero(procedure: &str) -> Self {
+        Error::DivisionByZero {
+            procedure: procedure.to_string(),
+            span: None,
+        }
+    }
+
+    pub fn too_large(procedure: &str) -> Self {
+        Error::TooLarge {
+            procedure: procedure.to_string(),
+            span: None,
+        }
+    }
+
+    pub fn recursion_too_deep() -> Self {
+        Error::RecursionTooDeep { span: None }
+    }
+
+    /// Returns where in the source the error happened, if known.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Error::Read { span, .. } => Some(span),
+            Error::Syntax { span, .. }
+            | Error::UnboundVariable { span, .. }
+            | Error::Arity { span, .. }
+            | Error::Type { span, .. }
+            | Error::DivisionByZero { span, .. }
+            | Error::TooLarge { span, .. }
+            | Error::RecursionTooDeep { span }
+            | Error::User { span, .. } => span.as_ref(),
+            Error::Io { .. } => None,
+        }
+    }
+
+    /// Attaches `location` to the error unless it already knows a more precise one.
+    pub fn with_span(mut self, location: &Span) -> Self {
+        match &mut self {
+            Error::Syntax { span, .. }
+            | Error::UnboundVariable { span, .. }
+            | Error::Arity { span, .. }
+            | Error::Type { span, .. }
+            | Error::DivisionByZero { span, .. }
+            | Error::TooLarge { span, .. }
+            | Error::RecursionTooDeep { span }
+            | Error::User { span, .. } => {
+                if span.is_none() {
+                    *span = Some(location.clone());
+                }
+            }
+            Error::Read { .. } | Error::Io { .. } => (),
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Read { message, .. } => write!(f, "Read error: {}", message)?,
+            Error::Syntax {
+                keyword, message, ..
+            } => write!(f, "Bad syntax in {}: {}", keyword, message)?,
+            Error::UnboundVariable { name, .. } => write!(f, "Unbound variable: {}", name)?,
+            Error::Arity {
+                procedure,
+                expected,
+                given,
+                ..
+            } => write!(
+                f,
+                "{}: expected {} argument(s), got {}",
+                procedure, expected, given
+            )?,
+            Error::Type {
+                procedure,
+                expected,
+                value,
+                ..
+            } => write!(f, "{}: expected {}, got {}", procedure, expected, value)?,
+            Error::DivisionByZero { procedure, .. } => {
+                write!(f, "{}: division by zero", procedure)?
+            }
+            Error::TooLarge { procedure, .. } => write!(f, "{}: result too large", procedure)?,
+            Error::RecursionTooDeep { .. } => write!(f, "Recursion too deep")?,
+            Error::Io { path, message } => write!(f, "Can't read {}: {}", path, message)?,
+            Error::User {
+                message, irritants, ..
+            } => {
+                write!(f, "{}", message)?;
+                for irritant in irritants {
+                    write!(f, " {}", irritant)?;
+                }
+            }
+        }
+        match self.span() {
+            Some(span) => write!(f, " (at {})", span),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
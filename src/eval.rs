@@ -0,0 +1,920 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::error::{Error, Span};
+use crate::lists::spread_apply_args;
+use crate::macros::Macro;
+use crate::reader::Datum;
+use crate::types::{CaseLambda, Procedure, Symbol, Value};
+use crate::vm;
+
+/// The result of evaluating an expression up to its tail position: either a final value, or an
+/// expression or call that is left to carry out in place of the current one, so that tail calls
+/// don't grow the Rust stack.
+pub(crate) enum Step {
+    Done(Value),
+    TailCall(Datum, Rc<Environment>),
+    /// A call the virtual machine left in tail position, with the span of the call.
+    Apply(Value, Vec<Value>, Option<Span>),
+}
+
+pub fn eval(exp: &Datum, env: Rc<Environment>) -> Result<Value, Error> {
+    nested(|| finish(eval_step(exp, env)?))
+}
+
+/// Carries out tail calls, whether they come from evaluated or compiled code, until one of them
+/// gives a value.
+pub(crate) fn finish(mut step: Step) -> Result<Value, Error> {
+    loop {
+        step = match step {
+            Step::Done(val) => return Ok(val),
+            Step::TailCall(exp, env) => eval_step(&exp, env)?,
+            Step::Apply(proc, args, span) => match apply_step(&proc, args) {
+                Ok(step) => step,
+                Err(e) => {
+                    return Err(match span {
+                        Some(span) => e.with_span(&span),
+                        None => e,
+                    })
+                }
+            },
+        }
+    }
+}
+
+/// How deeply evaluations, or calls in the virtual machine, may nest before failing. The Rust
+/// stack grows as needed, so this only stops runaway recursion from taking all the memory there
+/// is.
+pub(crate) const MAX_DEPTH: usize = 100_000;
+/// The stack space an evaluation needs to start without switching to a new stack segment.
+const RED_ZONE: usize = 128 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs `f`, which evaluates Scheme code, on a new stack segment if the current one is nearly
+/// full, so that deep non-tail recursion can't overflow the stack.
+pub(crate) fn nested<T>(f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    let depth = DEPTH.with(|cell| cell.replace(cell.get() + 1));
+    let res = if depth < MAX_DEPTH {
+        stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, f)
+    } else {
+        Err(Error::recursion_too_deep())
+    };
+    DEPTH.with(|cell| cell.set(depth));
+    res
+}
+
+fn eval_step(exp: &Datum, env: Rc<Environment>) -> Result<Step, Error> {
+    let val = match exp {
+        // Handle self-evaluating expressions
+        Datum::Boolean(b) => Value::Boolean(*b),
+        Datum::Integer(n) => Value::Integer(*n),
+        Datum::Float(n) => Value::Float(*n),
+        Datum::String(s) => Value::String(s.to_string()),
+        Datum::Char(c) => Value::Char(*c),
+        Datum::BigInteger(_) | Datum::Rational(_) => exp.to_value(),
+        Datum::Vector(_) | Datum::Bytevector(_) => exp.to_value(),
+        Datum::DottedList(_, _) => {
+            return Err(Error::syntax("application", "can't evaluate a dotted list"))
+        }
+
+        // Check in the environment
+        Datum::Symbol(_) | Datum::Renamed(_) => {
+            lookup(exp, &env).ok_or_else(|| Error::unbound(exp.keyword().unwrap()))?
+        }
+
+        Datum::List(items, span) => {
+            return eval_list(exp, items, span, env).map_err(|e| e.with_span(span));
+        }
+    };
+    Ok(Step::Done(val))
+}
+
+/// Looks up the value of an identifier. An identifier renamed by a macro expansion that isn't
+/// bound by the expansion refers to its original symbol in the macro's environment.
+fn lookup(identifier: &Datum, env: &Rc<Environment>) -> Option<Value> {
+    match identifier {
+        Datum::Symbol(name) => env.get_value(name),
+        Datum::Renamed(renamed) => env
+            .get_value(&renamed.name)
+            .or_else(|| lookup(&renamed.symbol, &renamed.env)),
+        _ => None,
+    }
+}
+
+/// Rebinds an identifier the way [`lookup`] finds it. Returns `false` if it is unbound.
+fn assign(identifier: &Datum, env: &Rc<Environment>, val: Value) -> bool {
+    match identifier {
+        Datum::Symbol(name) => env.set_value(name, val),
+        Datum::Renamed(renamed) => {
+            env.set_value(&renamed.name, val.clone()) || assign(&renamed.symbol, &renamed.env, val)
+        }
+        _ => false,
+    }
+}
+
+fn eval_list(
+    exp: &Datum,
+    items: &[Datum],
+    span: &Span,
+    env: Rc<Environment>,
+) -> Result<Step, Error> {
+    let (head, args) = match items.split_first() {
+        Some(split) => split,
+        None => return Ok(Step::Done(Value::Null)),
+    };
+    let proc = match head.keyword() {
+        // A binding of the keyword, like a parameter named `list` or a macro named `unless`,
+        // shadows the special form
+        Some(keyword) => match lookup(head, &env) {
+            Some(Value::Macro(mac)) => return Ok(Step::TailCall(mac.expand(exp, span)?, env)),
+            Some(proc) => proc,
+            None => match eval_special_form(keyword, args, env.clone()) {
+                Some(res) => return res,
+                None => return Err(Error::unbound(keyword)),
+            },
+        },
+        None => eval(head, env.clone())?,
+    };
+
+    // Handle procedure call
+    apply_step(&proc, eval_args(args, env)?)
+}
+
+/// Evaluates the special form introduced by `keyword`, or returns `None` if `keyword` does not
+/// name one.
+fn eval_special_form(
+    keyword: &str,
+    args: &[Datum],
+    env: Rc<Environment>,
+) -> Option<Result<Step, Error>> {
+    let res = match keyword {
+        "quote" => eval_quote(args).map(Step::Done),
+        "quasiquote" => match args {
+            [template] => quasiquote(template, 1, &env).map(Step::Done),
+            _ => Err(Error::syntax("quasiquote", "expected exactly one template")),
+        },
+        "load" => eval_load(args, env).map(Step::Done),
+        "define" => eval_define(args, env).map(Step::Done),
+        "set!" => eval_set(args, env).map(Step::Done),
+        "lambda" => eval_lambda(args, env).map(Step::Done),
+        "case-lambda" => eval_case_lambda(args, env).map(Step::Done),
+        "let" => eval_let(args, env),
+        "let*" => eval_let_star(args, env),
+        "letrec" => eval_letrec("letrec", args, env),
+        "letrec*" => eval_letrec("letrec*", args, env),
+        "define-syntax" => eval_define_syntax(args, env).map(Step::Done),
+        "let-syntax" => eval_let_syntax("let-syntax", args, env),
+        "letrec-syntax" => eval_let_syntax("letrec-syntax", args, env),
+        "if" => eval_if(args, env),
+        "cond" => eval_cond(args, env),
+        "case" => eval_case(args, env),
+        "and" => eval_and(args, env),
+        "or" => eval_or(args, env),
+        "when" => eval_when("when", true, args, env),
+        "unless" => eval_when("unless", false, args, env),
+        "do" => eval_do(args, env),
+        "begin" => eval_sequence(args, env),
+        _ => return None,
+    };
+    Some(res)
+}
+
+fn eval_quote(args: &[Datum]) -> Result<Value, Error> {
+    match args {
+        [datum] => Ok(datum.to_value()),
+        _ => Err(Error::syntax("quote", "expected exactly one datum")),
+    }
+}
+
+/// If `datum` is `(keyword x)`, returns `x`.
+pub(crate) fn unwrap_form<'a>(datum: &'a Datum, keyword: &str) -> Option<&'a Datum> {
+    match datum {
+        Datum::List(items, _) => match &items[..] {
+            [head, x] if head.keyword() == Some(keyword) => Some(x),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Builds the value of a quasiquote template. `depth` counts the enclosing quasiquotes, only
+/// unquotes at depth 1 are evaluated.
+fn quasiquote(template: &Datum, depth: usize, env: &Rc<Environment>) -> Result<Value, Error> {
+    if let Some(x) = unwrap_form(template, "unquote") {
+        return match depth {
+            1 => eval(x, env.clone()),
+            _ => Ok(tagged("unquote", quasiquote(x, depth - 1, env)?)),
+        };
+    }
+    if let Some(x) = unwrap_form(template, "quasiquote") {
+        return Ok(tagged("quasiquote", quasiquote(x, depth + 1, env)?));
+    }
+    if let Some(x) = unwrap_form(template, "unquote-splicing") {
+        // Inside a list, splicing is handled by quasiquote_list
+        return match depth {
+            1 => Err(Error::syntax("unquote-splicing", "not in a list or vector")),
+            _ => Ok(tagged("unquote-splicing", quasiquote(x, depth - 1, env)?)),
+        };
+    }
+    match template {
+        Datum::List(items, _) => quasiquote_list(items, Value::Null, depth, env),
+        Datum::DottedList(items, tail) => {
+            let tail = quasiquote(tail, depth, env)?;
+            quasiquote_list(items, tail, depth, env)
+        }
+        Datum::Vector(items) => {
+            let mut elements = Vec::new();
+            let mut list = quasiquote_list(items, Value::Null, depth, env)?;
+            while let Value::Pair(pair) = list {
+                elements.push(pair.car());
+                list = pair.cdr();
+            }
+            Ok(Value::vector(elements))
+        }
+        _ => Ok(template.to_value()),
+    }
+}
+
+fn quasiquote_list(
+    items: &[Datum],
+    tail: Value,
+    depth: usize,
+    env: &Rc<Environment>,
+) -> Result<Value, Error> {
+    // The reader turns `(a . ,b)` into `(a unquote b)`
+    let (items, tail) = match items {
+        [_, .., splice, _] if depth == 1 && splice.keyword() == Some("unquote-splicing") => {
+            return Err(Error::syntax("unquote-splicing", "not in a list or vector"));
+        }
+        [init @ .., unquote, x] if unquote.keyword() == Some("unquote") => {
+            let tail = match depth {
+                1 => eval(x, env.clone())?,
+                _ => tagged("unquote", quasiquote(x, depth - 1, env)?),
+            };
+            (init, tail)
+        }
+        _ => (items, tail),
+    };
+
+    let mut res = tail;
+    for item in items.iter().rev() {
+        match unwrap_form(item, "unquote-splicing") {
+            Some(x) if depth == 1 => {
+                let spliced = eval(x, env.clone())?;
+                let mut elements = Vec::new();
+                let mut list = spliced.clone();
+                while let Value::Pair(pair) = list {
+                    elements.push(pair.car());
+                    list = pair.cdr();
+                }
+                if !matches!(list, Value::Null) {
+                    return Err(Error::wrong_type("unquote-splicing", "list", &spliced));
+                }
+                for element in elements.into_iter().rev() {
+                    res = Value::cons(element, res);
+                }
+            }
+            Some(x) => {
+                let x = tagged("unquote-splicing", quasiquote(x, depth - 1, env)?);
+                res = Value::cons(x, res);
+            }
+            None => res = Value::cons(quasiquote(item, depth, env)?, res),
+        }
+    }
+    Ok(res)
+}
+
+/// Builds the list `(keyword val)`.
+fn tagged(keyword: &str, val: Value) -> Value {
+    Value::cons(
+        Value::Symbol(Symbol::new(keyword)),
+        Value::cons(val, Value::Null),
+    )
+}
+
+fn eval_load(args: &[Datum], env: Rc<Environment>) -> Result<Value, Error> {
+    let filename = match args {
+        [Datum::Symbol(name)] => name.as_str(),
+        [Datum::String(name)] => name,
+        _ => return Err(Error::syntax("load", "expected a file name")),
+    };
+    let mut last = Value::Null;
+    for exp in crate::read_from_file::read(filename)? {
+        last = eval(&exp, env.clone())?;
+    }
+    Ok(last)
+}
+
+fn eval_define(args: &[Datum], env: Rc<Environment>) -> Result<Value, Error> {
+    // (define (name params...) body...) is sugar for (define name (lambda (params...) body...)),
+    // and (define (name params... . rest) body...) for (define name (lambda (params... . rest)
+    // body...))
+    let (signature, rest, body) = match args {
+        [Datum::List(signature, _), body @ ..] => (signature, None, body),
+        [Datum::DottedList(signature, rest), body @ ..] => (signature, Some(&**rest), body),
+        [var, exp] if var.as_symbol().is_some() => {
+            let var_value = eval(exp, env.clone())?;
+            env.add_value(var.as_symbol().unwrap(), var_value.clone());
+            return Ok(var_value);
+        }
+        _ => return Err(Error::syntax("define", "expected a name and a value")),
+    };
+    let (name, params) = match signature.split_first() {
+        Some((name, params)) if name.as_symbol().is_some() => (name.as_symbol().unwrap(), params),
+        _ => return Err(Error::syntax("define", "expected a procedure name")),
+    };
+    if body.is_empty() {
+        return Err(Error::syntax("define", "expected a procedure body"));
+    }
+    let proc = make_procedure(Some(name), params, rest, body, env.clone())?;
+    let proc = Value::procedure(proc);
+    env.add_value(name, proc.clone());
+    Ok(proc)
+}
+
+fn eval_set(args: &[Datum], env: Rc<Environment>) -> Result<Value, Error> {
+    match args {
+        [var, exp] if var.as_symbol().is_some() => {
+            let var_value = eval(exp, env.clone())?;
+            if !assign(var, &env, var_value.clone()) {
+                return Err(Error::unbound(var.keyword().unwrap()));
+            }
+            Ok(var_value)
+        }
+        _ => Err(Error::syntax("set!", "expected a variable and a value")),
+    }
+}
+
+fn eval_lambda(args: &[Datum], env: Rc<Environment>) -> Result<Value, Error> {
+    match args {
+        [formals, body @ ..] if !body.is_empty() => {
+            let (params, rest) = split_formals("lambda", formals)?;
+            let proc = make_procedure(None, params, rest, body, env)?;
+            Ok(Value::procedure(proc))
+        }
+        _ => Err(Error::syntax("lambda", "expected parameters and a body")),
+    }
+}
+
+/// Splits the formals of a lambda into the required parameters and the rest parameter, if any:
+/// `(a b)` has none, `(a b . rest)` has one, and a lone `args` takes every argument.
+fn split_formals<'a>(
+    keyword: &str,
+    formals: &'a Datum,
+) -> Result<(&'a [Datum], Option<&'a Datum>), Error> {
+    match formals {
+        Datum::List(params, _) => Ok((params, None)),
+        Datum::DottedList(params, rest) => Ok((params, Some(rest))),
+        _ if formals.as_symbol().is_some() => Ok((&[], Some(formals))),
+        _ => Err(Error::syntax(keyword, "expected a parameter list")),
+    }
+}
+
+/// `(case-lambda (formals body...) ...)` makes a procedure that runs the first clause whose
+/// formals accept the arguments it is called with.
+fn eval_case_lambda(args: &[Datum], env: Rc<Environment>) -> Result<Value, Error> {
+    let mut clauses = Vec::new();
+    for clause in args {
+        match clause {
+            Datum::List(clause, _) if clause.len() >= 2 => {
+                let (params, rest) = split_formals("case-lambda", &clause[0])?;
+                clauses.push(make_procedure(
+                    None,
+                    params,
+                    rest,
+                    &clause[1..],
+                    env.clone(),
+                )?);
+            }
+            _ => {
+                return Err(Error::syntax(
+                    "case-lambda",
+                    "clauses must be (formals body...)",
+                ))
+            }
+        }
+    }
+    Ok(Value::case_lambda(CaseLambda { clauses }))
+}
+
+/// Builds a closure capturing `env`, the environment the procedure is defined in.
+fn make_procedure(
+    name: Option<&str>,
+    params: &[Datum],
+    rest: Option<&Datum>,
+    body: &[Datum],
+    env: Rc<Environment>,
+) -> Result<Procedure, Error> {
+    let mut names = Vec::new();
+    for param in params.iter().chain(rest) {
+        match param.as_symbol() {
+            Some(name) => names.push(name.to_string()),
+            None => return Err(Error::syntax("lambda", "parameters must be symbols")),
+        }
+    }
+    check_distinct("lambda", names.iter().map(String::as_str))?;
+    let rest = rest.and_then(|_| names.pop());
+    Ok(Procedure {
+        name: name.map(str::to_string),
+        params: names,
+        rest,
+        body: body.into(),
+        env,
+    })
+}
+
+/// Splits a `((name init) ...)` binding list into names and initializers. Only `let*` may bind
+/// the same name twice.
+fn parse_bindings<'a>(
+    keyword: &str,
+    bindings: &'a Datum,
+) -> Result<Vec<(&'a str, &'a Datum)>, Error> {
+    let bindings = match bindings {
+        Datum::List(bindings, _) => bindings,
+        _ => return Err(Error::syntax(keyword, "expected a list of bindings")),
+    };
+    let bindings = bindings
+        .iter()
+        .map(|binding| match binding {
+            Datum::List(binding, _) => match &binding[..] {
+                [name, init] if name.as_symbol().is_some() => Ok((name.as_symbol().unwrap(), init)),
+                _ => Err(Error::syntax(keyword, "bindings must be (name value)")),
+            },
+            _ => Err(Error::syntax(keyword, "bindings must be (name value)")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if keyword != "let*" {
+        check_distinct(keyword, bindings.iter().map(|(name, _)| *name))?;
+    }
+    Ok(bindings)
+}
+
+/// Fails with a syntax error if `names`, the variables bound by one `keyword` form, has a
+/// duplicate.
+pub(crate) fn check_distinct<'a>(
+    keyword: &str,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    for name in names {
+        if !seen.insert(name) {
+            return Err(Error::syntax(
+                keyword,
+                &format!("duplicate binding of {}", name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn eval_let(args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    if let [name, bindings, body @ ..] = args {
+        if let Some(name) = name.as_symbol() {
+            return eval_named_let(name, bindings, body, env);
+        }
+    }
+    let (bindings, body) = match args {
+        [bindings, body @ ..] if !body.is_empty() => (parse_bindings("let", bindings)?, body),
+        _ => return Err(Error::syntax("let", "expected bindings and a body")),
+    };
+    let new_env = Environment::new_with_enclosing(env.clone());
+    for (name, init) in bindings {
+        new_env.add_value(name, eval(init, env.clone())?);
+    }
+    eval_body(body, new_env)
+}
+
+/// `(let name ((var init) ...) body...)` binds `name` to a procedure taking the variables, visible
+/// only inside the body, and calls it with the initial values.
+fn eval_named_let(
+    name: &str,
+    bindings: &Datum,
+    body: &[Datum],
+    env: Rc<Environment>,
+) -> Result<Step, Error> {
+    let bindings = parse_bindings("let", bindings)?;
+    if body.is_empty() {
+        return Err(Error::syntax("let", "expected a body"));
+    }
+    let args = bindings
+        .iter()
+        .map(|(_, init)| eval(init, env.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let loop_env = Environment::new_with_enclosing(env);
+    let proc = Value::procedure(Procedure {
+        name: Some(name.to_string()),
+        params: bindings.iter().map(|(var, _)| var.to_string()).collect(),
+        rest: None,
+        body: body.into(),
+        env: loop_env.clone(),
+    });
+    loop_env.add_value(name, proc.clone());
+    apply_step(&proc, args)
+}
+
+/// Every binding gets its own frame, so each initializer sees the variables bound before it.
+fn eval_let_star(args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    let (bindings, body) = match args {
+        [bindings, body @ ..] if !body.is_empty() => (parse_bindings("let*", bindings)?, body),
+        _ => return Err(Error::syntax("let*", "expected bindings and a body")),
+    };
+    let mut new_env = Environment::new_with_enclosing(env);
+    for (name, init) in bindings {
+        let val = eval(init, new_env.clone())?;
+        new_env = Environment::new_with_enclosing(new_env);
+        new_env.add_value(name, val);
+    }
+    eval_body(body, new_env)
+}
+
+/// All the initializers are evaluated in the frame the variables are bound in, so they can refer
+/// to each other. `letrec*` binds each variable as soon as its value is known, while `letrec`
+/// binds them all at once after evaluating every initializer.
+fn eval_letrec(keyword: &str, args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    let (bindings, body) = match args {
+        [bindings, body @ ..] if !body.is_empty() => (parse_bindings(keyword, bindings)?, body),
+        _ => return Err(Error::syntax(keyword, "expected bindings and a body")),
+    };
+    let new_env = Environment::new_with_enclosing(env);
+    for (name, _) in &bindings {
+        new_env.declare(name);
+    }
+    let mut vals = Vec::new();
+    for (name, init) in &bindings {
+        let val = eval(init, new_env.clone())?;
+        if keyword == "letrec*" {
+            new_env.add_value(name, val);
+        } else {
+            vals.push(val);
+        }
+    }
+    for ((name, _), val) in bindings.iter().zip(vals) {
+        new_env.add_value(name, val);
+    }
+    eval_body(body, new_env)
+}
+
+fn eval_define_syntax(args: &[Datum], env: Rc<Environment>) -> Result<Value, Error> {
+    match args {
+        [name, spec] if name.as_symbol().is_some() => {
+            let name = name.as_symbol().unwrap();
+            let mac = Value::syntax(Macro::new(name, spec, env.clone())?);
+            env.add_value(name, mac.clone());
+            Ok(mac)
+        }
+        _ => Err(Error::syntax(
+            "define-syntax",
+            "expected a name and a syntax-rules form",
+        )),
+    }
+}
+
+/// Binds macros in a new frame for the body. The templates of `letrec-syntax` macros are closed
+/// over that frame, so they can refer to each other, while `let-syntax` ones see the outer one.
+fn eval_let_syntax(keyword: &str, args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    let (bindings, body) = match args {
+        [bindings, body @ ..] if !body.is_empty() => (parse_bindings(keyword, bindings)?, body),
+        _ => return Err(Error::syntax(keyword, "expected bindings and a body")),
+    };
+    let new_env = Environment::new_with_enclosing(env.clone());
+    let macro_env = if keyword == "letrec-syntax" {
+        new_env.clone()
+    } else {
+        env
+    };
+    for (name, spec) in bindings {
+        let mac = Macro::new(name, spec, macro_env.clone())?;
+        new_env.add_value(name, Value::syntax(mac));
+    }
+    eval_body(body, new_env)
+}
+
+fn eval_if(args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(Error::syntax(
+            "if",
+            "expected a test, a consequent and an optional alternative",
+        ));
+    }
+
+    if eval(&args[0], env.clone())?.is_true() {
+        Ok(Step::TailCall(args[1].clone(), env))
+    } else {
+        match args.get(2) {
+            Some(exp) => Ok(Step::TailCall(exp.clone(), env)),
+            None => Ok(Step::Done(Value::Null)),
+        }
+    }
+}
+
+/// Evaluates the body of the first clause whose test is true. A clause can be `(test body...)`,
+/// just `(test)` to return the value of the test, `(test => receiver)` to call `receiver` with
+/// that value, or a final `(else body...)`.
+fn eval_cond(args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    if args.is_empty() {
+        return Err(Error::syntax("cond", "expected at least one clause"));
+    }
+    for (i, clause) in args.iter().enumerate() {
+        let (test, body) = match clause {
+            Datum::List(clause, _) if !clause.is_empty() => (&clause[0], &clause[1..]),
+            _ => return Err(Error::syntax("cond", "clauses must be (test body...)")),
+        };
+        if test.keyword() == Some("else") {
+            if i != args.len() - 1 {
+                return Err(Error::syntax("cond", "else must be the last clause"));
+            }
+            return eval_sequence(body, env);
+        }
+        let val = eval(test, env.clone())?;
+        if val.is_true() {
+            return eval_clause_body("cond", val, body, env);
+        }
+    }
+    Ok(Step::Done(Value::Null))
+}
+
+/// `(case key clause...)` evaluates the body of the first clause listing a datum `eqv?` to the
+/// value of `key`. Clauses are `((datum...) body...)` or a final `(else body...)`, and either
+/// can pass the key to a procedure with `=> receiver` instead of a body.
+fn eval_case(args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    let (key, clauses) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(Error::syntax("case", "expected a key and clauses")),
+    };
+    let key = eval(key, env.clone())?;
+    for (i, clause) in clauses.iter().enumerate() {
+        let (data, body) = match clause {
+            Datum::List(clause, _) if clause.len() >= 2 => (&clause[0], &clause[1..]),
+            _ => {
+                return Err(Error::syntax(
+                    "case",
+                    "clauses must be ((datum...) body...)",
+                ))
+            }
+        };
+        let matches = match data {
+            Datum::List(data, _) => data.iter().any(|datum| datum.to_value().is_eqv(&key)),
+            _ if data.keyword() == Some("else") => {
+                if i != clauses.len() - 1 {
+                    return Err(Error::syntax("case", "else must be the last clause"));
+                }
+                true
+            }
+            _ => {
+                return Err(Error::syntax(
+                    "case",
+                    "clauses must be ((datum...) body...)",
+                ))
+            }
+        };
+        if matches {
+            return eval_clause_body("case", key, body, env);
+        }
+    }
+    Ok(Step::Done(Value::Null))
+}
+
+/// Evaluates the body of a `cond` or `case` clause that was selected by `val`.
+fn eval_clause_body(
+    keyword: &str,
+    val: Value,
+    body: &[Datum],
+    env: Rc<Environment>,
+) -> Result<Step, Error> {
+    match body {
+        [] => Ok(Step::Done(val)),
+        [arrow, receiver] if arrow.keyword() == Some("=>") => {
+            let receiver = eval(receiver, env)?;
+            apply_step(&receiver, vec![val])
+        }
+        [arrow, ..] if arrow.keyword() == Some("=>") => {
+            Err(Error::syntax(keyword, "expected one receiver after =>"))
+        }
+        _ => eval_sequence(body, env),
+    }
+}
+
+/// `(do ((var init step)...) (test result...) command...)` binds the variables to their initial
+/// values, then runs the commands and rebinds the variables to their steps until the test is
+/// true. Every iteration gets a fresh frame, so closures capture that iteration's variables.
+fn eval_do(args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    let (specs, exit, commands) = match args {
+        [Datum::List(specs, _), Datum::List(exit, _), commands @ ..] if !exit.is_empty() => {
+            (specs, exit, commands)
+        }
+        _ => {
+            return Err(Error::syntax(
+                "do",
+                "expected variables, a test with results and commands",
+            ))
+        }
+    };
+    let mut vars = Vec::new();
+    for spec in specs.iter() {
+        match spec {
+            Datum::List(spec, _) => match &spec[..] {
+                [var, init] if var.as_symbol().is_some() => {
+                    vars.push((var.as_symbol().unwrap(), init, None))
+                }
+                [var, init, step] if var.as_symbol().is_some() => {
+                    vars.push((var.as_symbol().unwrap(), init, Some(step)))
+                }
+                _ => return Err(Error::syntax("do", "variables must be (name init step)")),
+            },
+            _ => return Err(Error::syntax("do", "variables must be (name init step)")),
+        }
+    }
+    check_distinct("do", vars.iter().map(|(var, _, _)| *var))?;
+
+    let mut loop_env = Environment::new_with_enclosing(env.clone());
+    for (var, init, _) in &vars {
+        loop_env.add_value(var, eval(init, env.clone())?);
+    }
+    loop {
+        if eval(&exit[0], loop_env.clone())?.is_true() {
+            return eval_sequence(&exit[1..], loop_env);
+        }
+        for command in commands {
+            eval(command, loop_env.clone())?;
+        }
+        let next_env = Environment::new_with_enclosing(env.clone());
+        for (var, _, step) in &vars {
+            let val = match step {
+                Some(step) => eval(step, loop_env.clone())?,
+                None => loop_env.get_value(var).unwrap(),
+            };
+            next_env.add_value(var, val);
+        }
+        loop_env = next_env;
+    }
+}
+
+/// Evaluates the operands from left to right, stopping at the first false one. The last operand
+/// is in tail position.
+fn eval_and(args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    let (last, init) = match args.split_last() {
+        Some(split) => split,
+        None => return Ok(Step::Done(Value::Boolean(true))),
+    };
+    for exp in init {
+        let val = eval(exp, env.clone())?;
+        if !val.is_true() {
+            return Ok(Step::Done(val));
+        }
+    }
+    Ok(Step::TailCall(last.clone(), env))
+}
+
+/// Evaluates the operands from left to right, stopping at the first true one. The last operand
+/// is in tail position.
+fn eval_or(args: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    let (last, init) = match args.split_last() {
+        Some(split) => split,
+        None => return Ok(Step::Done(Value::Boolean(false))),
+    };
+    for exp in init {
+        let val = eval(exp, env.clone())?;
+        if val.is_true() {
+            return Ok(Step::Done(val));
+        }
+    }
+    Ok(Step::TailCall(last.clone(), env))
+}
+
+/// `(when test body...)` evaluates the body if the test is true, `(unless test body...)` if it
+/// is false.
+fn eval_when(
+    keyword: &str,
+    expected: bool,
+    args: &[Datum],
+    env: Rc<Environment>,
+) -> Result<Step, Error> {
+    let (test, body) = match args.split_first() {
+        Some((test, body)) if !body.is_empty() => (test, body),
+        _ => return Err(Error::syntax(keyword, "expected a test and a body")),
+    };
+    if eval(test, env.clone())?.is_true() == expected {
+        eval_sequence(body, env)
+    } else {
+        Ok(Step::Done(Value::Null))
+    }
+}
+
+fn eval_args(args: &[Datum], env: Rc<Environment>) -> Result<Vec<Value>, Error> {
+    args.iter().map(|arg| eval(arg, env.clone())).collect()
+}
+
+/// Evaluates the body of a procedure or of a `let`-family form, in the new frame `env`.
+/// Definitions in the body have `letrec*` semantics: they are local to `env` from the start of
+/// the body, and each is bound as soon as it's evaluated.
+fn eval_body(body: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    declare_definitions(body, &env);
+    eval_sequence(body, env)
+}
+
+/// Evaluates a sequence of expressions up to the last one, which is left in tail position.
+fn eval_sequence(body: &[Datum], env: Rc<Environment>) -> Result<Step, Error> {
+    let (last, init) = match body.split_last() {
+        Some(split) => split,
+        None => return Ok(Step::Done(Value::Null)),
+    };
+    for exp in init {
+        eval(exp, env.clone())?;
+    }
+    Ok(Step::TailCall(last.clone(), env))
+}
+
+/// Declares in `env` every name defined by the definitions in `body`, including those spliced in
+/// by `begin`.
+fn declare_definitions(body: &[Datum], env: &Environment) {
+    for exp in body {
+        let items = match exp {
+            Datum::List(items, _) => items,
+            _ => continue,
+        };
+        match items.first().and_then(Datum::keyword) {
+            Some("define") | Some("define-syntax") => {
+                let name = match items.get(1) {
+                    Some(Datum::List(signature, _)) | Some(Datum::DottedList(signature, _)) => {
+                        signature.first()
+                    }
+                    name => name,
+                };
+                if let Some(name) = name.and_then(Datum::as_symbol) {
+                    env.declare(name);
+                }
+            }
+            Some("begin") => declare_definitions(&items[1..], env),
+            _ => (),
+        }
+    }
+}
+
+/// Applies `proc` to already evaluated arguments.
+pub fn apply(proc: &Value, args: Vec<Value>) -> Result<Value, Error> {
+    nested(|| finish(apply_step(proc, args)?))
+}
+
+/// Calls `proc` with already evaluated arguments, evaluating its body up to the last expression,
+/// which is returned as a tail call.
+fn apply_step(proc: &Value, args: Vec<Value>) -> Result<Step, Error> {
+    match proc {
+        Value::Procedure(proc) => {
+            proc.arity().check(proc.display_name(), args.len())?;
+            enter(proc, args)
+        }
+        Value::CaseLambda(case_lambda) => {
+            let clause = case_lambda
+                .clauses
+                .iter()
+                .find(|clause| clause.arity().accepts(args.len()));
+            match clause {
+                Some(clause) => enter(clause, args),
+                None => Err(Error::arity(
+                    "#<procedure>",
+                    case_lambda.arity(),
+                    args.len(),
+                )),
+            }
+        }
+        Value::Primitive(primitive) if primitive.is_apply() => {
+            let (proc, args) = spread_apply_args(args)?;
+            apply_step(&proc, args)
+        }
+        Value::Primitive(primitive) => Ok(Step::Done(primitive.call(args)?)),
+        Value::Closure(closure) => vm::call(closure, args),
+        _ => Err(Error::wrong_type("application", "procedure", proc)),
+    }
+}
+
+/// Binds the parameters of `proc` to arguments it is known to accept. The body is evaluated in a
+/// new frame enclosed by the environment the procedure was created in, not the caller's.
+fn enter(proc: &Procedure, mut args: Vec<Value>) -> Result<Step, Error> {
+    let new_env = Environment::new_with_enclosing(proc.env.clone());
+    if let Some(rest) = &proc.rest {
+        let rest_args = args.split_off(proc.params.len());
+        let list = rest_args
+            .into_iter()
+            .rev()
+            .fold(Value::Null, |list, arg| Value::cons(arg, list));
+        new_env.add_value(rest, list);
+    }
+    proc.params.iter().zip(args).for_each(|(param, arg)| {
+        new_env.add_value(param.as_str(), arg);
+    });
+    eval_body(&proc.body, new_env)
+}
+
+/// Calls the procedure bound to `name` with already evaluated arguments.
+pub fn call_procedure(name: &str, args: Vec<Value>, env: Rc<Environment>) -> Result<Value, Error> {
+    match env.get_value(name) {
+        Some(proc) => apply(&proc, args),
+        None => Err(Error::unbound(name)),
+    }
+}
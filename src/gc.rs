@@ -0,0 +1,409 @@
+//! A tracing collector for reference cycles.
+//!
+//! Values are reference counted, which frees everything except cycles, such as a procedure bound
+//! in the environment or frame it closes over, or a list whose last pair was `set-cdr!` to its
+//! first one. Every object that can be part of a cycle is registered here when it is allocated. A
+//! collection treats the objects that are referenced from outside the registered ones as roots,
+//! marks everything reachable from them, and frees the unmarked ones by emptying them, which
+//! breaks the cycles keeping them alive.
+//!
+//! The registry holds a weak reference to each object. An object that isn't part of a cycle is
+//! still dropped as soon as it becomes unreachable, but the memory block of its reference count
+//! is only freed once its entry is pruned. Pruning runs whenever the registry has doubled in size
+//! since the last pruning, so registering an object costs amortized constant time, and at most
+//! about as many dead blocks as live objects are kept around.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use crate::environment::Environment;
+use crate::macros::Macro;
+use crate::types::{Arity, CaseLambda, Pair, Procedure, Symbol, Value};
+use crate::vm::{Closure, Frame};
+
+/// A collection runs automatically once this many registered objects are alive, or twice as
+/// many as the last collection found alive if that is more.
+const MIN_THRESHOLD: usize = 100_000;
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap {
+        objects: Vec::new(),
+        threshold: MIN_THRESHOLD,
+        prune_at: MIN_THRESHOLD,
+        statistics: Statistics::default(),
+    });
+}
+
+struct Heap {
+    objects: Vec<Object>,
+    threshold: usize,
+    /// The number of entries at which the ones of freed objects are removed.
+    prune_at: usize,
+    statistics: Statistics,
+}
+
+/// Counts describing the work of the collector on the current thread.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// The number of collections run so far.
+    pub collections: usize,
+    /// The number of objects registered so far.
+    pub allocated: usize,
+    /// The number of objects found alive by the last collection.
+    pub live: usize,
+    /// The number of unreachable objects freed by collections so far. Objects that aren't part of
+    /// a cycle are freed as soon as they become unreachable, and aren't counted.
+    pub reclaimed: usize,
+}
+
+/// An object registered with the collector.
+pub(crate) enum Object {
+    Pair(Weak<Pair>),
+    Vector(Weak<RefCell<Vec<Value>>>),
+    Environment(Weak<Environment>),
+    Procedure(Weak<Procedure>),
+    CaseLambda(Weak<CaseLambda>),
+    Macro(Weak<Macro>),
+    Frame(Weak<Frame>),
+    Closure(Weak<Closure>),
+}
+
+impl From<&Rc<Pair>> for Object {
+    fn from(pair: &Rc<Pair>) -> Self {
+        Object::Pair(Rc::downgrade(pair))
+    }
+}
+
+impl From<&Rc<RefCell<Vec<Value>>>> for Object {
+    fn from(vector: &Rc<RefCell<Vec<Value>>>) -> Self {
+        Object::Vector(Rc::downgrade(vector))
+    }
+}
+
+impl From<&Rc<Environment>> for Object {
+    fn from(env: &Rc<Environment>) -> Self {
+        Object::Environment(Rc::downgrade(env))
+    }
+}
+
+impl From<&Rc<Procedure>> for Object {
+    fn from(proc: &Rc<Procedure>) -> Self {
+        Object::Procedure(Rc::downgrade(proc))
+    }
+}
+
+impl From<&Rc<CaseLambda>> for Object {
+    fn from(proc: &Rc<CaseLambda>) -> Self {
+        Object::CaseLambda(Rc::downgrade(proc))
+    }
+}
+
+impl From<&Rc<Macro>> for Object {
+    fn from(mac: &Rc<Macro>) -> Self {
+        Object::Macro(Rc::downgrade(mac))
+    }
+}
+
+impl From<&Rc<Frame>> for Object {
+    fn from(frame: &Rc<Frame>) -> Self {
+        Object::Frame(Rc::downgrade(frame))
+    }
+}
+
+impl From<&Rc<Closure>> for Object {
+    fn from(closure: &Rc<Closure>) -> Self {
+        Object::Closure(Rc::downgrade(closure))
+    }
+}
+
+impl Object {
+    fn is_alive(&self) -> bool {
+        let count = match self {
+            Object::Pair(pair) => pair.strong_count(),
+            Object::Vector(vector) => vector.strong_count(),
+            Object::Environment(env) => env.strong_count(),
+            Object::Procedure(proc) => proc.strong_count(),
+            Object::CaseLambda(proc) => proc.strong_count(),
+            Object::Macro(mac) => mac.strong_count(),
+            Object::Frame(frame) => frame.strong_count(),
+            Object::Closure(closure) => closure.strong_count(),
+        };
+        count > 0
+    }
+
+    fn upgrade(&self) -> Option<Live> {
+        match self {
+            Object::Pair(pair) => pair.upgrade().map(Live::Pair),
+            Object::Vector(vector) => vector.upgrade().map(Live::Vector),
+            Object::Environment(env) => env.upgrade().map(Live::Environment),
+            Object::Procedure(proc) => proc.upgrade().map(Live::Procedure),
+            Object::CaseLambda(proc) => proc.upgrade().map(Live::CaseLambda),
+            Object::Macro(mac) => mac.upgrade().map(Live::Macro),
+            Object::Frame(frame) => frame.upgrade().map(Live::Frame),
+            Object::Closure(closure) => closure.upgrade().map(Live::Closure),
+        }
+    }
+}
+
+/// A registered object that hasn't been freed yet.
+enum Live {
+    Pair(Rc<Pair>),
+    Vector(Rc<RefCell<Vec<Value>>>),
+    Environment(Rc<Environment>),
+    Procedure(Rc<Procedure>),
+    CaseLambda(Rc<CaseLambda>),
+    Macro(Rc<Macro>),
+    Frame(Rc<Frame>),
+    Closure(Rc<Closure>),
+}
+
+impl Live {
+    fn downgrade(&self) -> Object {
+        match self {
+            Live::Pair(pair) => pair.into(),
+            Live::Vector(vector) => vector.into(),
+            Live::Environment(env) => env.into(),
+            Live::Procedure(proc) => proc.into(),
+            Live::CaseLambda(proc) => proc.into(),
+            Live::Macro(mac) => mac.into(),
+            Live::Frame(frame) => frame.into(),
+            Live::Closure(closure) => closure.into(),
+        }
+    }
+
+    fn id(&self) -> usize {
+        match self {
+            Live::Pair(pair) => Rc::as_ptr(pair) as usize,
+            Live::Vector(vector) => Rc::as_ptr(vector) as usize,
+            Live::Environment(env) => Rc::as_ptr(env) as usize,
+            Live::Procedure(proc) => Rc::as_ptr(proc) as usize,
+            Live::CaseLambda(proc) => Rc::as_ptr(proc) as usize,
+            Live::Macro(mac) => Rc::as_ptr(mac) as usize,
+            Live::Frame(frame) => Rc::as_ptr(frame) as usize,
+            Live::Closure(closure) => Rc::as_ptr(closure) as usize,
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Live::Pair(pair) => Rc::strong_count(pair),
+            Live::Vector(vector) => Rc::strong_count(vector),
+            Live::Environment(env) => Rc::strong_count(env),
+            Live::Procedure(proc) => Rc::strong_count(proc),
+            Live::CaseLambda(proc) => Rc::strong_count(proc),
+            Live::Macro(mac) => Rc::strong_count(mac),
+            Live::Frame(frame) => Rc::strong_count(frame),
+            Live::Closure(closure) => Rc::strong_count(closure),
+        }
+    }
+
+    /// Returns the ids of the objects this one references, or `None` if it is being modified and
+    /// can't be inspected.
+    fn references(&self) -> Option<Vec<usize>> {
+        let mut ids = Vec::new();
+        match self {
+            Live::Pair(pair) => {
+                ids.extend(value_id(&*pair.car.try_borrow().ok()?));
+                ids.extend(value_id(&*pair.cdr.try_borrow().ok()?));
+            }
+            Live::Vector(vector) => {
+                ids.extend(vector.try_borrow().ok()?.iter().filter_map(value_id))
+            }
+            Live::Environment(env) => {
+                if !env.try_for_each_value(|value| ids.extend(value_id(value))) {
+                    return None;
+                }
+                ids.extend(
+                    env.enclosing()
+                        .map(|enclosing| Rc::as_ptr(enclosing) as usize),
+                );
+            }
+            Live::Procedure(proc) => ids.push(Rc::as_ptr(&proc.env) as usize),
+            Live::CaseLambda(proc) => ids.extend(
+                proc.clauses
+                    .iter()
+                    .map(|clause| Rc::as_ptr(&clause.env) as usize),
+            ),
+            Live::Macro(mac) => ids.push(Rc::as_ptr(mac.env()) as usize),
+            Live::Frame(frame) => {
+                let slots = frame.slots.try_borrow().ok()?;
+                ids.extend(slots.iter().flatten().filter_map(value_id));
+                ids.extend(
+                    frame
+                        .parent
+                        .as_ref()
+                        .map(|parent| Rc::as_ptr(parent) as usize),
+                );
+            }
+            Live::Closure(closure) => {
+                ids.push(Rc::as_ptr(&closure.frame) as usize);
+                ids.push(Rc::as_ptr(&closure.globals) as usize);
+            }
+        }
+        Some(ids)
+    }
+
+    /// Empties the object, returning the values it held so they can be dropped once nothing is
+    /// borrowed anymore. Procedures and macros can't be emptied, but can't form cycles on their
+    /// own either.
+    fn clear(&self) -> Vec<Value> {
+        match self {
+            Live::Pair(pair) => match (pair.car.try_borrow_mut(), pair.cdr.try_borrow_mut()) {
+                (Ok(mut car), Ok(mut cdr)) => vec![
+                    std::mem::replace(&mut *car, Value::Null),
+                    std::mem::replace(&mut *cdr, Value::Null),
+                ],
+                _ => Vec::new(),
+            },
+            Live::Vector(vector) => match vector.try_borrow_mut() {
+                Ok(mut items) => std::mem::take(&mut *items),
+                Err(_) => Vec::new(),
+            },
+            Live::Environment(env) => env.take_values(),
+            Live::Frame(frame) => match frame.slots.try_borrow_mut() {
+                Ok(mut slots) => slots.iter_mut().filter_map(Option::take).collect(),
+                Err(_) => Vec::new(),
+            },
+            Live::Procedure(_) | Live::CaseLambda(_) | Live::Macro(_) | Live::Closure(_) => {
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// The id of the registered object a value refers to, if any.
+fn value_id(value: &Value) -> Option<usize> {
+    match value {
+        Value::Pair(pair) => Some(Rc::as_ptr(pair) as usize),
+        Value::Vector(vector) => Some(Rc::as_ptr(vector) as usize),
+        Value::Procedure(proc) => Some(Rc::as_ptr(proc) as usize),
+        Value::CaseLambda(proc) => Some(Rc::as_ptr(proc) as usize),
+        Value::Macro(mac) => Some(Rc::as_ptr(mac) as usize),
+        Value::Closure(closure) => Some(Rc::as_ptr(closure) as usize),
+        _ => None,
+    }
+}
+
+/// Registers a newly allocated object, pruning the entries of freed objects if the registry has
+/// grown enough, and collecting if enough registered objects are still alive after that.
+pub(crate) fn track(object: impl Into<Object>) {
+    let due = HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.objects.push(object.into());
+        heap.statistics.allocated += 1;
+        if heap.objects.len() < heap.prune_at {
+            return false;
+        }
+        heap.objects.retain(Object::is_alive);
+        heap.prune_at = MIN_THRESHOLD.max(2 * heap.objects.len());
+        heap.objects.len() >= heap.threshold
+    });
+    if due {
+        collect();
+    }
+}
+
+/// Frees every registered object that can't be reached from outside the registered objects,
+/// returning how many there were.
+pub fn collect() -> usize {
+    let objects = HEAP.with(|heap| std::mem::take(&mut heap.borrow_mut().objects));
+    let live: Vec<Live> = objects.iter().filter_map(Object::upgrade).collect();
+    drop(objects);
+
+    let index: HashMap<usize, usize> = live
+        .iter()
+        .enumerate()
+        .map(|(i, object)| (object.id(), i))
+        .collect();
+    let references: Vec<Option<Vec<usize>>> = live
+        .iter()
+        .map(|object| {
+            let ids = object.references()?;
+            Some(ids.iter().filter_map(|id| index.get(id).copied()).collect())
+        })
+        .collect();
+    let mut internal = vec![0; live.len()];
+    for &i in references.iter().flatten().flatten() {
+        internal[i] += 1;
+    }
+
+    // Upgrading added one reference to every object. Any other reference that doesn't come from
+    // a registered object is held by Rust code, such as the interpreter's global environment or
+    // a value being evaluated, which makes the object a root. So does being modified right now.
+    let mut marked = vec![false; live.len()];
+    let mut pending = Vec::new();
+    for (i, object) in live.iter().enumerate() {
+        if references[i].is_none() || object.strong_count() - 1 > internal[i] {
+            marked[i] = true;
+            pending.push(i);
+        }
+    }
+    while let Some(i) = pending.pop() {
+        for &j in references[i].iter().flatten() {
+            if !marked[j] {
+                marked[j] = true;
+                pending.push(j);
+            }
+        }
+    }
+
+    let mut garbage = Vec::new();
+    let mut survivors = Vec::new();
+    for (object, marked) in live.iter().zip(marked) {
+        if marked {
+            survivors.push(object.downgrade());
+        } else {
+            garbage.extend(object.clear());
+        }
+    }
+    let reclaimed = live.len() - survivors.len();
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        let live = survivors.len();
+        // Objects registered while collecting are kept as well
+        survivors.append(&mut heap.objects);
+        heap.objects = survivors;
+        heap.threshold = MIN_THRESHOLD.max(2 * live);
+        heap.prune_at = MIN_THRESHOLD.max(2 * heap.objects.len());
+        heap.statistics.collections += 1;
+        heap.statistics.live = live;
+        heap.statistics.reclaimed += reclaimed;
+    });
+    drop(garbage);
+    drop(live);
+    reclaimed
+}
+
+/// Returns the statistics of the collector on the current thread.
+pub fn statistics() -> Statistics {
+    HEAP.with(|heap| heap.borrow().statistics)
+}
+
+/// Binds the procedures controlling the collector in `env`.
+pub fn add_gc_procedures(env: &Environment) {
+    // (gc) runs a collection and returns the number of objects it freed
+    env.define_primitive("gc", Arity::Exactly(0), |_| {
+        Ok(Value::Integer(collect() as i64))
+    });
+    // (gc-statistics) returns an association list of the collector statistics
+    env.define_primitive("gc-statistics", Arity::Exactly(0), |_| {
+        let statistics = statistics();
+        let entries = [
+            ("collections", statistics.collections),
+            ("allocated", statistics.allocated),
+            ("live", statistics.live),
+            ("reclaimed", statistics.reclaimed),
+        ];
+        Ok(entries
+            .iter()
+            .rev()
+            .fold(Value::Null, |list, (name, count)| {
+                let entry = Value::cons(
+                    Value::Symbol(Symbol::new(name)),
+                    Value::Integer(*count as i64),
+                );
+                Value::cons(entry, list)
+            }))
+    });
+}
@@ -0,0 +1,90 @@
+use std::rc::Rc;
+
+use crate::compiler::compile;
+use crate::environment::{add_primitives, Environment};
+use crate::error::Error;
+use crate::eval::{call_procedure, eval};
+use crate::read_from_file;
+use crate::reader::{self, Datum};
+use crate::types::{Arity, Value};
+use crate::vm;
+
+/// An embeddable Scheme interpreter owning its own global environment.
+pub struct Interpreter {
+    global_env: Rc<Environment>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let global_env = Environment::new_global();
+        add_primitives(&global_env);
+        Interpreter { global_env }
+    }
+
+    /// Returns the global environment every top-level expression is evaluated in.
+    pub fn global_env(&self) -> Rc<Environment> {
+        self.global_env.clone()
+    }
+
+    /// Evaluates every expression in `src` in the global environment, returning the value of
+    /// the last one.
+    pub fn eval_str(&self, src: &str) -> Result<Value, Error> {
+        self.eval_all(reader::read(src)?)
+    }
+
+    /// Like [`Interpreter::eval_str`], but attributes source positions in errors to `name`.
+    pub fn eval_source(&self, src: &str, name: &str) -> Result<Value, Error> {
+        self.eval_all(reader::read_source(src, Some(name))?)
+    }
+
+    /// Evaluates every top-level expression in `file`, returning the value of the last one.
+    pub fn eval_file(&self, file: &str) -> Result<Value, Error> {
+        self.eval_all(read_from_file::read(file)?)
+    }
+
+    /// Like [`Interpreter::eval_str`], but compiles each expression to bytecode and runs it on
+    /// the virtual machine. Expressions the compiler doesn't support are evaluated as usual.
+    pub fn run_str(&self, src: &str) -> Result<Value, Error> {
+        let mut last = Value::Null;
+        for exp in reader::read(src)? {
+            last = match compile(&exp, &self.global_env) {
+                Some(code) => vm::run(code, self.global_env.clone())?,
+                None => eval(&exp, self.global_env.clone())?,
+            };
+        }
+        Ok(last)
+    }
+
+    fn eval_all(&self, exps: Vec<Datum>) -> Result<Value, Error> {
+        let mut last = Value::Null;
+        for exp in exps {
+            last = eval(&exp, self.global_env.clone())?;
+        }
+        Ok(last)
+    }
+
+    /// Binds `name` to `value` in the global environment.
+    pub fn define_global(&self, name: &str, value: Value) {
+        self.global_env.add_value(name, value);
+    }
+
+    /// Binds `name` in the global environment to a primitive procedure implemented in Rust. See
+    /// [`Environment::define_primitive`].
+    pub fn define_primitive<F>(&self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(Vec<Value>) -> Result<Value, Error> + 'static,
+    {
+        self.global_env.define_primitive(name, arity, function);
+    }
+
+    /// Calls the procedure bound to `name` with already evaluated arguments.
+    pub fn call_procedure(&self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        call_procedure(name, args, self.global_env.clone())
+    }
+}
@@ -1,4 +1,19 @@
+pub mod chars;
+pub mod compiler;
 pub mod environment;
+pub mod error;
+pub mod eval;
+pub mod gc;
+pub mod interpreter;
+pub mod lists;
+pub mod macros;
+pub mod numbers;
 pub mod read_from_file;
+pub mod reader;
+pub mod strings;
 pub mod types;
+pub mod vectors;
+pub mod vm;
 
+pub use error::Error;
+pub use interpreter::Interpreter;
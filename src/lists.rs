@@ -0,0 +1,316 @@
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::eval::apply;
+use crate::strings::index_arg;
+use crate::types::Arity;
+use crate::types::Value;
+
+/// Binds the list procedures in `env`.
+pub fn add_list_procedures(env: &Environment) {
+    env.define_primitive("memq", Arity::Exactly(2), member("memq", Value::is_eq));
+    env.define_primitive("memv", Arity::Exactly(2), member("memv", Value::is_eqv));
+    env.define_primitive(
+        "member",
+        Arity::Between(2, 3),
+        member("member", Value::is_equal),
+    );
+    env.define_primitive("assq", Arity::Exactly(2), assoc("assq", Value::is_eq));
+    env.define_primitive("assv", Arity::Exactly(2), assoc("assv", Value::is_eqv));
+    env.define_primitive(
+        "assoc",
+        Arity::Between(2, 3),
+        assoc("assoc", Value::is_equal),
+    );
+    env.define_primitive("length", Arity::Exactly(1), |args| {
+        Ok(Value::Integer(list_arg("length", &args[0])?.len() as i64))
+    });
+    env.define_primitive("append", Arity::AtLeast(0), |args| {
+        // The last argument is shared rather than copied, and doesn't have to be a list
+        let (last, init) = match args.split_last() {
+            Some(split) => split,
+            None => return Ok(Value::Null),
+        };
+        let mut res = last.clone();
+        for list in init.iter().rev() {
+            res = vec_to_list(list_arg("append", list)?, res);
+        }
+        Ok(res)
+    });
+    env.define_primitive("reverse", Arity::Exactly(1), |args| {
+        let items = list_arg("reverse", &args[0])?;
+        Ok(items
+            .into_iter()
+            .fold(Value::Null, |list, item| Value::cons(item, list)))
+    });
+    env.define_primitive("list-tail", Arity::Exactly(2), |args| {
+        list_tail("list-tail", &args[0], &args[1])
+    });
+    env.define_primitive("list-ref", Arity::Exactly(2), |args| {
+        match list_tail("list-ref", &args[0], &args[1])? {
+            Value::Pair(pair) => Ok(pair.car()),
+            _ => Err(Error::wrong_type("list-ref", "index in range", &args[1])),
+        }
+    });
+    env.define_primitive("last-pair", Arity::Exactly(1), |args| {
+        let mut pair = match &args[0] {
+            Value::Pair(pair) => pair.clone(),
+            _ => return Err(Error::wrong_type("last-pair", "pair", &args[0])),
+        };
+        while let Value::Pair(next) = pair.cdr() {
+            pair = next;
+        }
+        Ok(Value::Pair(pair))
+    });
+    env.define_primitive("list-copy", Arity::Exactly(1), |args| {
+        // Improper lists are copied up to their tail
+        let mut items = Vec::new();
+        let mut list = args[0].clone();
+        while let Value::Pair(pair) = list {
+            items.push(pair.car());
+            list = pair.cdr();
+        }
+        Ok(vec_to_list(items, list))
+    });
+    // Calls made through the evaluator and the virtual machine don't get here, but spread the
+    // arguments themselves so that the procedure is called in tail position
+    env.define_primitive("apply", Arity::AtLeast(2), |args| {
+        let (proc, args) = spread_apply_args(args)?;
+        apply(&proc, args)
+    });
+    env.define_primitive("map", Arity::AtLeast(2), |args| {
+        let res = map_lists("map", &args[0], &args[1..])?;
+        Ok(vec_to_list(res, Value::Null))
+    });
+    env.define_primitive("for-each", Arity::AtLeast(2), |args| {
+        map_lists("for-each", &args[0], &args[1..])?;
+        Ok(Value::Null)
+    });
+    env.define_primitive("filter", Arity::Exactly(2), |args| {
+        let mut res = Vec::new();
+        for item in list_arg("filter", &args[1])? {
+            if apply(&args[0], vec![item.clone()])?.is_true() {
+                res.push(item);
+            }
+        }
+        Ok(vec_to_list(res, Value::Null))
+    });
+    // (reduce f initial list) combines the elements as (f element accumulator), starting
+    // from the first element. initial is only returned for an empty list.
+    env.define_primitive("reduce", Arity::Exactly(3), |args| {
+        let mut items = list_arg("reduce", &args[2])?.into_iter();
+        let mut acc = match items.next() {
+            Some(first) => first,
+            None => return Ok(args[1].clone()),
+        };
+        for item in items {
+            acc = apply(&args[0], vec![item, acc])?;
+        }
+        Ok(acc)
+    });
+    // (fold-left f initial list...) computes (f (f initial a1 b1...) a2 b2...)...
+    env.define_primitive("fold-left", Arity::AtLeast(3), |args| {
+        let lists = list_args("fold-left", &args[2..])?;
+        let mut acc = args[1].clone();
+        for i in 0..lists.iter().map(Vec::len).min().unwrap_or(0) {
+            let mut call_args = vec![acc];
+            call_args.extend(lists.iter().map(|list| list[i].clone()));
+            acc = apply(&args[0], call_args)?;
+        }
+        Ok(acc)
+    });
+    // (fold-right f initial list...) computes (f a1 b1... (f a2 b2... initial))
+    env.define_primitive("fold-right", Arity::AtLeast(3), |args| {
+        let lists = list_args("fold-right", &args[2..])?;
+        let mut acc = args[1].clone();
+        for i in (0..lists.iter().map(Vec::len).min().unwrap_or(0)).rev() {
+            let mut call_args: Vec<Value> = lists.iter().map(|list| list[i].clone()).collect();
+            call_args.push(acc);
+            acc = apply(&args[0], call_args)?;
+        }
+        Ok(acc)
+    });
+    // (sort sequence less?) returns a sorted copy of a list or vector. The sort is stable.
+    env.define_primitive("sort", Arity::Exactly(2), |args| {
+        let less = |a: &Value, b: &Value| -> Result<bool, Error> {
+            Ok(apply(&args[1], vec![a.clone(), b.clone()])?.is_true())
+        };
+        match &args[0] {
+            Value::Vector(items) => {
+                let items = items.borrow().clone();
+                Ok(Value::vector(merge_sort(items, &less)?))
+            }
+            list => Ok(vec_to_list(
+                merge_sort(list_arg("sort", list)?, &less)?,
+                Value::Null,
+            )),
+        }
+    });
+}
+
+/// Collects the elements of a proper list. A circular list is rejected like an improper one.
+pub fn list_arg(proc: &str, arg: &Value) -> Result<Vec<Value>, Error> {
+    let mut items = Vec::new();
+    let mut list = arg.clone();
+    // Floyd's cycle detection: the tortoise moves one pair for every two the list does, so the
+    // list catches up with it only if it loops
+    let mut tortoise = arg.clone();
+    while let Value::Pair(pair) = list {
+        items.push(pair.car());
+        list = pair.cdr();
+        if items.len() % 2 == 0 {
+            if let Value::Pair(slow) = tortoise {
+                tortoise = slow.cdr();
+            }
+            if let (Value::Pair(fast), Value::Pair(slow)) = (&list, &tortoise) {
+                if Rc::ptr_eq(fast, slow) {
+                    return Err(Error::wrong_type(proc, "list", arg));
+                }
+            }
+        }
+    }
+    match list {
+        Value::Null => Ok(items),
+        _ => Err(Error::wrong_type(proc, "list", arg)),
+    }
+}
+
+/// Splits the arguments of `(apply proc a b list)` into the procedure and the arguments it's
+/// called with: a, b and the elements of list.
+pub fn spread_apply_args(mut args: Vec<Value>) -> Result<(Value, Vec<Value>), Error> {
+    Arity::AtLeast(2).check("apply", args.len())?;
+    let spread = list_arg("apply", &args.pop().unwrap())?;
+    let proc = args.remove(0);
+    args.extend(spread);
+    Ok((proc, args))
+}
+
+fn list_args(proc: &str, args: &[Value]) -> Result<Vec<Vec<Value>>, Error> {
+    args.iter().map(|arg| list_arg(proc, arg)).collect()
+}
+
+/// Builds a list of `items` ending in `tail`.
+pub fn vec_to_list(items: Vec<Value>, tail: Value) -> Value {
+    items
+        .into_iter()
+        .rev()
+        .fold(tail, |list, item| Value::cons(item, list))
+}
+
+/// Drops the first `k` pairs of a list.
+fn list_tail(proc: &str, list: &Value, k: &Value) -> Result<Value, Error> {
+    let k = index_arg(proc, k)?;
+    let mut res = list.clone();
+    for _ in 0..k {
+        res = match res {
+            Value::Pair(pair) => pair.cdr(),
+            _ => {
+                return Err(Error::wrong_type(
+                    proc,
+                    "index in range",
+                    &Value::Integer(k as i64),
+                ))
+            }
+        };
+    }
+    Ok(res)
+}
+
+/// Calls `proc` with the first elements of every list, then the second ones, and so on until the
+/// shortest list runs out. Returns the results.
+fn map_lists(name: &str, proc: &Value, lists: &[Value]) -> Result<Vec<Value>, Error> {
+    let lists = list_args(name, lists)?;
+    let len = lists.iter().map(Vec::len).min().unwrap_or(0);
+    (0..len)
+        .map(|i| apply(proc, lists.iter().map(|list| list[i].clone()).collect()))
+        .collect()
+}
+
+/// A stable merge sort with a comparison that can fail.
+fn merge_sort(
+    mut items: Vec<Value>,
+    less: &dyn Fn(&Value, &Value) -> Result<bool, Error>,
+) -> Result<Vec<Value>, Error> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = merge_sort(items.split_off(items.len() / 2), less)?;
+    let left = merge_sort(items, less)?;
+    let mut res = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // Taking from the left unless the right element is strictly smaller keeps the sort stable
+        if less(r, l)? {
+            res.push(right.next().unwrap());
+        } else {
+            res.push(left.next().unwrap());
+        }
+    }
+    res.extend(left);
+    res.extend(right);
+    Ok(res)
+}
+
+/// Compares `x` to `y` with the procedure passed as the optional third argument of `member` and
+/// `assoc`, or with `default` if there is none.
+fn equivalent(
+    compare: Option<&Value>,
+    default: fn(&Value, &Value) -> bool,
+    x: &Value,
+    y: &Value,
+) -> Result<bool, Error> {
+    match compare {
+        Some(compare) => Ok(apply(compare, vec![x.clone(), y.clone()])?.is_true()),
+        None => Ok(default(x, y)),
+    }
+}
+
+/// Builds `memq`, `memv` or `member`, which return the first sublist of a list whose car is
+/// equivalent to an object, or `#f`.
+fn member(
+    name: &'static str,
+    default: fn(&Value, &Value) -> bool,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        let mut list = args[1].clone();
+        while let Value::Pair(pair) = &list {
+            if equivalent(args.get(2), default, &args[0], &pair.car())? {
+                return Ok(list);
+            }
+            let next = pair.cdr();
+            list = next;
+        }
+        match list {
+            Value::Null => Ok(Value::Boolean(false)),
+            _ => Err(Error::wrong_type(name, "list", &args[1])),
+        }
+    }
+}
+
+/// Builds `assq`, `assv` or `assoc`, which return the first pair of an association list whose
+/// car is equivalent to a key, or `#f`.
+fn assoc(
+    name: &'static str,
+    default: fn(&Value, &Value) -> bool,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        let mut list = args[1].clone();
+        while let Value::Pair(pair) = list {
+            let entry = pair.car();
+            match &entry {
+                Value::Pair(entry_pair) => {
+                    if equivalent(args.get(2), default, &args[0], &entry_pair.car())? {
+                        return Ok(entry);
+                    }
+                }
+                _ => return Err(Error::wrong_type(name, "association list", &args[1])),
+            }
+            list = pair.cdr();
+        }
+        match list {
+            Value::Null => Ok(Value::Boolean(false)),
+            _ => Err(Error::wrong_type(name, "association list", &args[1])),
+        }
+    }
+}
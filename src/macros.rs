@@ -0,0 +1,503 @@
+use core::fmt;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::environment::Environment;
+use crate::error::{Error, Span};
+use crate::eval::check_distinct;
+use crate::reader::Datum;
+
+/// An identifier inserted into the code by a macro expansion. Binding forms bind it under its
+/// fresh `name`, so it can never capture a variable written by the macro's user. Where it isn't
+/// bound by the expansion itself, it refers to `symbol` in the environment the macro was defined
+/// in, so the user can't capture it either.
+pub struct Renamed {
+    pub name: Rc<str>,
+    pub symbol: Datum,
+    pub env: Rc<Environment>,
+}
+
+impl fmt::Debug for Renamed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Renamed")
+            .field("name", &self.name)
+            .field("symbol", &self.symbol)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A macro defined with `syntax-rules`.
+pub struct Macro {
+    pub name: String,
+    ellipsis: Rc<str>,
+    literals: Vec<Rc<str>>,
+    rules: Vec<(Datum, Datum)>,
+    env: Rc<Environment>,
+}
+
+impl fmt::Debug for Macro {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Macro")
+            .field("name", &self.name)
+            .field("literals", &self.literals)
+            .field("rules", &self.rules)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What a pattern variable matched. Variables followed by an ellipsis in the pattern match one
+/// binding per repetition.
+#[derive(Clone)]
+enum Binding {
+    One(Datum),
+    Many(Vec<Binding>),
+}
+
+type Bindings = HashMap<Rc<str>, Binding>;
+
+impl Macro {
+    /// The environment the templates are closed over.
+    pub(crate) fn env(&self) -> &Rc<Environment> {
+        &self.env
+    }
+
+    /// Builds the macro `name` from a `(syntax-rules (literal ...) (pattern template) ...)` spec.
+    /// Templates are closed over `env`.
+    pub fn new(name: &str, spec: &Datum, env: Rc<Environment>) -> Result<Self, Error> {
+        let rest = match spec {
+            Datum::List(items, _)
+                if items.first().and_then(Datum::keyword) == Some("syntax-rules") =>
+            {
+                &items[1..]
+            }
+            _ => return Err(Error::syntax(name, "expected a syntax-rules form")),
+        };
+        // R7RS allows a custom ellipsis identifier before the literals
+        let (ellipsis, rest) = match rest {
+            [id, rest @ ..] if id.as_symbol().is_some() => (id.as_symbol().unwrap().into(), rest),
+            _ => ("...".into(), rest),
+        };
+        let (literals, rules) = match rest {
+            [Datum::List(literals, _), rules @ ..] => (literals, rules),
+            _ => return Err(Error::syntax("syntax-rules", "expected a list of literals")),
+        };
+        let literals = literals
+            .iter()
+            .map(|literal| match literal.as_symbol() {
+                Some(literal) => Ok(literal.into()),
+                None => Err(Error::syntax(
+                    "syntax-rules",
+                    "literals must be identifiers",
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+        let rules = rules
+            .iter()
+            .map(|rule| match rule {
+                Datum::List(rule, _) => match &rule[..] {
+                    [pattern @ (Datum::List(_, _) | Datum::DottedList(_, _)), template] => {
+                        Ok((pattern.clone(), template.clone()))
+                    }
+                    _ => Err(Error::syntax(
+                        "syntax-rules",
+                        "rules must be (pattern template)",
+                    )),
+                },
+                _ => Err(Error::syntax(
+                    "syntax-rules",
+                    "rules must be (pattern template)",
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+        let mac = Macro {
+            name: name.to_string(),
+            ellipsis,
+            literals,
+            rules,
+            env,
+        };
+        for (pattern, _) in &mac.rules {
+            let mut vars = Vec::new();
+            if let Datum::List(items, _) | Datum::DottedList(items, _) = pattern {
+                // The keyword position isn't a pattern variable
+                let tail = match pattern {
+                    Datum::DottedList(_, tail) => Some(&**tail),
+                    _ => None,
+                };
+                for item in items.iter().skip(1).chain(tail) {
+                    mac.pattern_variables(item, &mut vars);
+                }
+            }
+            check_distinct("syntax-rules", vars)?;
+        }
+        Ok(mac)
+    }
+
+    /// Collects the identifiers of `pattern` that bind parts of a form: all but `_`, the ellipsis
+    /// and the literals.
+    fn pattern_variables<'a>(&self, pattern: &'a Datum, vars: &mut Vec<&'a str>) {
+        match pattern {
+            Datum::Symbol(_) | Datum::Renamed(_) => {
+                let name = pattern.as_symbol().unwrap();
+                if name != "_"
+                    && !self.is_ellipsis(pattern)
+                    && !self.literals.iter().any(|literal| &**literal == name)
+                {
+                    vars.push(name);
+                }
+            }
+            Datum::List(items, _) | Datum::Vector(items) => {
+                for item in items.iter() {
+                    self.pattern_variables(item, vars);
+                }
+            }
+            Datum::DottedList(items, tail) => {
+                for item in items.iter() {
+                    self.pattern_variables(item, vars);
+                }
+                self.pattern_variables(tail, vars);
+            }
+            _ => (),
+        }
+    }
+
+    /// Rewrites the macro use `form` according to the first rule whose pattern matches it.
+    pub fn expand(&self, form: &Datum, span: &Span) -> Result<Datum, Error> {
+        for (pattern, template) in &self.rules {
+            let mut bindings = Bindings::new();
+            // The keyword position of the pattern is never matched
+            let matched = match (pattern, form) {
+                (Datum::List(pattern, _), Datum::List(form, _)) if !pattern.is_empty() => {
+                    self.match_seq(&pattern[1..], None, &form[1..], None, span, &mut bindings)
+                }
+                (Datum::DottedList(pattern, tail), Datum::List(form, _)) => self.match_seq(
+                    &pattern[1..],
+                    Some(tail),
+                    &form[1..],
+                    None,
+                    span,
+                    &mut bindings,
+                ),
+                _ => false,
+            };
+            if matched {
+                let mut renames = HashMap::new();
+                return self.expand_template(template, &bindings, &mut renames, span, false);
+            }
+        }
+        Err(Error::syntax(
+            &self.name,
+            "no syntax rule matches this form",
+        ))
+    }
+
+    fn is_ellipsis(&self, datum: &Datum) -> bool {
+        datum.as_symbol() == Some(&*self.ellipsis)
+    }
+
+    fn match_pattern(&self, pattern: &Datum, form: &Datum, span: &Span, b: &mut Bindings) -> bool {
+        match pattern {
+            Datum::Symbol(_) | Datum::Renamed(_) => {
+                let name = pattern.as_symbol().unwrap();
+                if name == "_" {
+                    return true;
+                }
+                if self.literals.iter().any(|literal| &**literal == name) {
+                    return form.keyword().is_some() && form.keyword() == pattern.keyword();
+                }
+                b.insert(name.into(), Binding::One(form.clone()));
+                true
+            }
+            Datum::List(pattern, _) => match form {
+                Datum::List(form, _) => self.match_seq(pattern, None, form, None, span, b),
+                Datum::DottedList(form, tail) => {
+                    self.match_seq(pattern, None, form, Some(tail), span, b)
+                }
+                _ => false,
+            },
+            Datum::DottedList(pattern, pattern_tail) => match form {
+                Datum::List(form, _) => {
+                    self.match_seq(pattern, Some(pattern_tail), form, None, span, b)
+                }
+                Datum::DottedList(form, tail) => {
+                    self.match_seq(pattern, Some(pattern_tail), form, Some(tail), span, b)
+                }
+                _ => false,
+            },
+            Datum::Vector(pattern) => match form {
+                Datum::Vector(form) => self.match_seq(pattern, None, form, None, span, b),
+                _ => false,
+            },
+            Datum::Boolean(p) => matches!(form, Datum::Boolean(f) if p == f),
+            Datum::Integer(p) => matches!(form, Datum::Integer(f) if p == f),
+            Datum::BigInteger(p) => matches!(form, Datum::BigInteger(f) if p == f),
+            Datum::Rational(p) => matches!(form, Datum::Rational(f) if p == f),
+            Datum::Float(p) => matches!(form, Datum::Float(f) if p == f),
+            Datum::String(p) => matches!(form, Datum::String(f) if p == f),
+            Datum::Char(p) => matches!(form, Datum::Char(f) if p == f),
+            Datum::Bytevector(p) => matches!(form, Datum::Bytevector(f) if p == f),
+        }
+    }
+
+    /// Matches the elements of a list pattern, which may contain one ellipsis and a dotted tail,
+    /// against the elements and tail of a form.
+    fn match_seq(
+        &self,
+        pattern: &[Datum],
+        pattern_tail: Option<&Datum>,
+        form: &[Datum],
+        form_tail: Option<&Datum>,
+        span: &Span,
+        b: &mut Bindings,
+    ) -> bool {
+        let ellipsis_at = pattern
+            .iter()
+            .skip(1)
+            .position(|datum| self.is_ellipsis(datum));
+        let (before, repeated, after) = match ellipsis_at {
+            Some(i) => (&pattern[..i], Some(&pattern[i]), &pattern[i + 2..]),
+            None => (pattern, None, &pattern[..0]),
+        };
+
+        let min_len = before.len() + after.len();
+        if form.len() < min_len || (pattern_tail.is_none() && form_tail.is_some()) {
+            return false;
+        }
+        // Without an ellipsis or a tail to absorb them, extra elements can't match
+        if repeated.is_none() && pattern_tail.is_none() && form.len() != min_len {
+            return false;
+        }
+        let repeated_len = match repeated {
+            Some(_) => form.len() - min_len,
+            None => 0,
+        };
+        let (form_before, rest) = form.split_at(before.len());
+        let (form_repeated, rest) = rest.split_at(repeated_len);
+        let (form_after, form_rest) = rest.split_at(after.len());
+
+        for (pattern, form) in before.iter().zip(form_before) {
+            if !self.match_pattern(pattern, form, span, b) {
+                return false;
+            }
+        }
+        if let Some(repeated) = repeated {
+            let mut matches = Vec::new();
+            for form in form_repeated {
+                let mut m = Bindings::new();
+                if !self.match_pattern(repeated, form, span, &mut m) {
+                    return false;
+                }
+                matches.push(m);
+            }
+            for var in self.pattern_vars(repeated) {
+                let each = matches
+                    .iter_mut()
+                    .map(|m| m.remove(&var).unwrap())
+                    .collect();
+                b.insert(var, Binding::Many(each));
+            }
+        }
+        for (pattern, form) in after.iter().zip(form_after) {
+            if !self.match_pattern(pattern, form, span, b) {
+                return false;
+            }
+        }
+        match pattern_tail {
+            Some(pattern_tail) => {
+                let rest = match (form_rest, form_tail) {
+                    ([], Some(tail)) => tail.clone(),
+                    ([], None) => Datum::List(Rc::new([]), span.clone()),
+                    (rest, Some(tail)) => Datum::DottedList(rest.into(), Rc::new(tail.clone())),
+                    (rest, None) => Datum::List(rest.into(), span.clone()),
+                };
+                self.match_pattern(pattern_tail, &rest, span, b)
+            }
+            None => true,
+        }
+    }
+
+    /// Lists the pattern variables bound by `pattern`.
+    fn pattern_vars(&self, pattern: &Datum) -> Vec<Rc<str>> {
+        let mut vars = Vec::new();
+        self.collect_vars(pattern, &mut vars);
+        vars
+    }
+
+    fn collect_vars(&self, pattern: &Datum, vars: &mut Vec<Rc<str>>) {
+        match pattern {
+            Datum::Symbol(_) | Datum::Renamed(_) => {
+                let name = pattern.as_symbol().unwrap();
+                if name != "_"
+                    && !self.is_ellipsis(pattern)
+                    && !self.literals.iter().any(|literal| &**literal == name)
+                {
+                    vars.push(name.into());
+                }
+            }
+            Datum::List(items, _) | Datum::Vector(items) => {
+                items.iter().for_each(|item| self.collect_vars(item, vars))
+            }
+            Datum::DottedList(items, tail) => {
+                items.iter().for_each(|item| self.collect_vars(item, vars));
+                self.collect_vars(tail, vars);
+            }
+            _ => (),
+        }
+    }
+
+    /// Instantiates `template`. Identifiers that aren't pattern variables are renamed, once per
+    /// expansion, to keep the expansion hygienic. Inside `(... template)` ellipses are literal.
+    fn expand_template(
+        &self,
+        template: &Datum,
+        b: &Bindings,
+        renames: &mut HashMap<Rc<str>, Datum>,
+        span: &Span,
+        escaped: bool,
+    ) -> Result<Datum, Error> {
+        match template {
+            Datum::Symbol(_) | Datum::Renamed(_) => {
+                let name = template.as_symbol().unwrap();
+                match b.get(name) {
+                    Some(Binding::One(datum)) => Ok(datum.clone()),
+                    Some(Binding::Many(_)) => Err(Error::syntax(
+                        &self.name,
+                        "pattern variable used without an ellipsis",
+                    )),
+                    None => Ok(self.rename(template, renames)),
+                }
+            }
+            Datum::List(items, _) => match &items[..] {
+                [ellipsis, escaped_template] if !escaped && self.is_ellipsis(ellipsis) => {
+                    self.expand_template(escaped_template, b, renames, span, true)
+                }
+                _ => Ok(Datum::List(
+                    self.expand_items(items, b, renames, span, escaped)?.into(),
+                    span.clone(),
+                )),
+            },
+            Datum::DottedList(items, tail) => {
+                let mut items = self.expand_items(items, b, renames, span, escaped)?;
+                Ok(
+                    match self.expand_template(tail, b, renames, span, escaped)? {
+                        Datum::List(rest, _) => {
+                            items.extend(rest.iter().cloned());
+                            Datum::List(items.into(), span.clone())
+                        }
+                        Datum::DottedList(rest, tail) => {
+                            items.extend(rest.iter().cloned());
+                            Datum::DottedList(items.into(), tail)
+                        }
+                        tail => Datum::DottedList(items.into(), Rc::new(tail)),
+                    },
+                )
+            }
+            Datum::Vector(items) => Ok(Datum::Vector(
+                self.expand_items(items, b, renames, span, escaped)?.into(),
+            )),
+            _ => Ok(template.clone()),
+        }
+    }
+
+    fn expand_items(
+        &self,
+        items: &[Datum],
+        b: &Bindings,
+        renames: &mut HashMap<Rc<str>, Datum>,
+        span: &Span,
+        escaped: bool,
+    ) -> Result<Vec<Datum>, Error> {
+        let mut res = Vec::new();
+        let mut i = 0;
+        while i < items.len() {
+            let mut depth = 0;
+            while !escaped
+                && items
+                    .get(i + 1 + depth)
+                    .is_some_and(|datum| self.is_ellipsis(datum))
+            {
+                depth += 1;
+            }
+            if depth == 0 {
+                res.push(self.expand_template(&items[i], b, renames, span, escaped)?);
+            } else {
+                res.extend(self.expand_repeated(&items[i], b, renames, span, depth)?);
+            }
+            i += 1 + depth;
+        }
+        Ok(res)
+    }
+
+    /// Instantiates a template followed by `depth` ellipses once per repetition of the pattern
+    /// variables it uses.
+    fn expand_repeated(
+        &self,
+        template: &Datum,
+        b: &Bindings,
+        renames: &mut HashMap<Rc<str>, Datum>,
+        span: &Span,
+        depth: usize,
+    ) -> Result<Vec<Datum>, Error> {
+        let mut vars = Vec::new();
+        self.collect_vars(template, &mut vars);
+        let repeated: Vec<_> = vars
+            .into_iter()
+            .filter_map(|var| match b.get(&var) {
+                Some(Binding::Many(each)) => Some((var, each)),
+                _ => None,
+            })
+            .collect();
+        let len = match repeated.first() {
+            Some((_, each)) => each.len(),
+            None => {
+                return Err(Error::syntax(
+                    &self.name,
+                    "no pattern variable to repeat before an ellipsis",
+                ))
+            }
+        };
+        if repeated.iter().any(|(_, each)| each.len() != len) {
+            return Err(Error::syntax(
+                &self.name,
+                "pattern variables repeated a different number of times",
+            ));
+        }
+
+        let mut res = Vec::new();
+        for i in 0..len {
+            let mut b = b.clone();
+            for (var, each) in &repeated {
+                b.insert(var.clone(), each[i].clone());
+            }
+            if depth == 1 {
+                res.push(self.expand_template(template, &b, renames, span, false)?);
+            } else {
+                res.extend(self.expand_repeated(template, &b, renames, span, depth - 1)?);
+            }
+        }
+        Ok(res)
+    }
+
+    fn rename(&self, identifier: &Datum, renames: &mut HashMap<Rc<str>, Datum>) -> Datum {
+        let name = identifier.as_symbol().unwrap();
+        renames
+            .entry(name.into())
+            .or_insert_with(|| {
+                Datum::Renamed(Rc::new(Renamed {
+                    name: fresh_name(identifier.keyword().unwrap()),
+                    symbol: identifier.clone(),
+                    env: self.env.clone(),
+                }))
+            })
+            .clone()
+    }
+}
+
+/// Makes a name for a renamed identifier. The space guarantees that it can't clash with any
+/// symbol the reader produces.
+fn fresh_name(symbol: &str) -> Rc<str> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{} {}", symbol, n).into()
+}
@@ -0,0 +1,457 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, Num, One, ToPrimitive, Zero};
+
+use crate::types::Value;
+
+// Exact numbers are kept in the simplest representation that holds them: an `Integer` whenever
+// the value fits in an i64, a `BigInteger` otherwise, and a `Rational` only when the denominator
+// isn't 1. Code matching on `Value::Integer` can rely on this.
+
+/// Wraps an arbitrary-precision integer into a value.
+pub fn integer(n: BigInt) -> Value {
+    match n.to_i64() {
+        Some(n) => Value::Integer(n),
+        None => Value::BigInteger(Rc::new(n)),
+    }
+}
+
+/// Wraps an exact rational into a value.
+pub fn rational(q: BigRational) -> Value {
+    if q.denom().is_one() {
+        integer(q.to_integer())
+    } else {
+        Value::Rational(Rc::new(q))
+    }
+}
+
+pub fn is_number(v: &Value) -> bool {
+    matches!(
+        v,
+        Value::Integer(_) | Value::BigInteger(_) | Value::Rational(_) | Value::Float(_)
+    )
+}
+
+pub fn is_exact(v: &Value) -> bool {
+    matches!(
+        v,
+        Value::Integer(_) | Value::BigInteger(_) | Value::Rational(_)
+    )
+}
+
+pub fn is_zero(v: &Value) -> bool {
+    match v {
+        Value::Integer(n) => *n == 0,
+        Value::Float(n) => *n == 0.0,
+        _ => false,
+    }
+}
+
+/// Converts a number to the nearest float.
+pub fn to_f64(v: &Value) -> Option<f64> {
+    match v {
+        Value::Integer(n) => Some(*n as f64),
+        Value::BigInteger(n) => n.to_f64(),
+        Value::Rational(q) => q.to_f64(),
+        Value::Float(n) => Some(*n),
+        _ => None,
+    }
+}
+
+fn to_big(v: &Value) -> Option<BigInt> {
+    match v {
+        Value::Integer(n) => Some(BigInt::from(*n)),
+        Value::BigInteger(n) => Some(n.as_ref().clone()),
+        _ => None,
+    }
+}
+
+fn to_rational(v: &Value) -> Option<BigRational> {
+    match v {
+        Value::Rational(q) => Some(q.as_ref().clone()),
+        _ => to_big(v).map(BigRational::from_integer),
+    }
+}
+
+/// Converts a number to the exact number it denotes. Infinities and NaN have no exact
+/// counterpart.
+pub fn to_exact(v: &Value) -> Option<Value> {
+    match v {
+        Value::Float(n) => BigRational::from_float(*n).map(rational),
+        _ if is_exact(v) => Some(v.clone()),
+        _ => None,
+    }
+}
+
+/// Applies an operation at the lowest level of the tower both operands fit in: float if either
+/// is inexact, rational if either is a fraction, and integer otherwise.
+fn binary(
+    a: &Value,
+    b: &Value,
+    big: fn(BigInt, BigInt) -> BigInt,
+    ratio: fn(BigRational, BigRational) -> BigRational,
+    float: fn(f64, f64) -> f64,
+) -> Option<Value> {
+    if !is_number(a) || !is_number(b) {
+        return None;
+    }
+    if matches!(a, Value::Float(_)) || matches!(b, Value::Float(_)) {
+        return Some(Value::Float(float(to_f64(a)?, to_f64(b)?)));
+    }
+    if matches!(a, Value::Rational(_)) || matches!(b, Value::Rational(_)) {
+        return Some(rational(ratio(to_rational(a)?, to_rational(b)?)));
+    }
+    Some(integer(big(to_big(a)?, to_big(b)?)))
+}
+
+pub fn add(a: &Value, b: &Value) -> Option<Value> {
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        if let Some(n) = x.checked_add(*y) {
+            return Some(Value::Integer(n));
+        }
+    }
+    binary(a, b, |x, y| x + y, |x, y| x + y, |x, y| x + y)
+}
+
+pub fn sub(a: &Value, b: &Value) -> Option<Value> {
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        if let Some(n) = x.checked_sub(*y) {
+            return Some(Value::Integer(n));
+        }
+    }
+    binary(a, b, |x, y| x - y, |x, y| x - y, |x, y| x - y)
+}
+
+pub fn mul(a: &Value, b: &Value) -> Option<Value> {
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        if let Some(n) = x.checked_mul(*y) {
+            return Some(Value::Integer(n));
+        }
+    }
+    binary(a, b, |x, y| x * y, |x, y| x * y, |x, y| x * y)
+}
+
+/// Divides `a` by `b`, which must not be zero. Dividing exact numbers gives an exact result.
+pub fn div(a: &Value, b: &Value) -> Option<Value> {
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        if let Some(0) = x.checked_rem(*y) {
+            return x.checked_div(*y).map(Value::Integer);
+        }
+    }
+    if !is_number(a) || !is_number(b) {
+        return None;
+    }
+    if matches!(a, Value::Float(_)) || matches!(b, Value::Float(_)) {
+        return Some(Value::Float(to_f64(a)? / to_f64(b)?));
+    }
+    Some(rational(to_rational(a)? / to_rational(b)?))
+}
+
+/// The remainder of truncating division of `a` by `b`, which must not be zero.
+pub fn rem(a: &Value, b: &Value) -> Option<Value> {
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        if let Some(n) = x.checked_rem(*y) {
+            return Some(Value::Integer(n));
+        }
+    }
+    binary(a, b, |x, y| x % y, |x, y| x % y, |x, y| x % y)
+}
+
+pub fn negate(v: &Value) -> Option<Value> {
+    match v {
+        // 0 - 0.0 would be 0.0 rather than -0.0
+        Value::Float(n) => Some(Value::Float(-n)),
+        _ => sub(&Value::Integer(0), v),
+    }
+}
+
+/// Compares two numbers. Floats are compared with exact numbers by converting the latter.
+pub fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(x), Value::Integer(y)) => Some(x.cmp(y)),
+        _ if !is_number(a) || !is_number(b) => None,
+        (Value::Float(_), _) | (_, Value::Float(_)) => to_f64(a)?.partial_cmp(&to_f64(b)?),
+        _ => Some(to_rational(a)?.cmp(&to_rational(b)?)),
+    }
+}
+
+/// Formats a float so it can't be mistaken for an exact integer.
+pub fn format_float(n: f64) -> String {
+    if n.is_nan() {
+        "+nan.0".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "+inf.0" } else { "-inf.0" }.to_string()
+    } else if n.fract() != 0.0 {
+        n.to_string()
+    } else if n.abs() < 1e16 {
+        format!("{:.1}", n)
+    } else {
+        // Written out in full, a large float would have no decimal point
+        format!("{:e}", n)
+    }
+}
+
+/// Writes a number in the given radix. Only exact numbers can be written in a radix other than
+/// 10.
+pub fn number_to_string(v: &Value, radix: u32) -> Option<String> {
+    match v {
+        Value::Integer(n) => Some(BigInt::from(*n).to_str_radix(radix)),
+        Value::BigInteger(n) => Some(n.to_str_radix(radix)),
+        Value::Rational(q) => Some(format!(
+            "{}/{}",
+            q.numer().to_str_radix(radix),
+            q.denom().to_str_radix(radix)
+        )),
+        Value::Float(n) if radix == 10 => Some(format_float(*n)),
+        _ => None,
+    }
+}
+
+/// Parses a number literal written in `radix`: an integer, a fraction like `1/3`, or in radix 10
+/// a decimal. A `#e` or `#i` prefix forces the result to be exact or inexact.
+pub fn parse_number(atom: &str, radix: u32) -> Option<Value> {
+    let (exactness, atom) = match atom.get(..2) {
+        Some("#e" | "#E") => (Some(true), &atom[2..]),
+        Some("#i" | "#I") => (Some(false), &atom[2..]),
+        _ => (None, atom),
+    };
+    let n = match atom {
+        "+inf.0" => Value::Float(f64::INFINITY),
+        "-inf.0" => Value::Float(f64::NEG_INFINITY),
+        "+nan.0" | "-nan.0" => Value::Float(f64::NAN),
+        _ => parse_real(atom, radix)?,
+    };
+    match exactness {
+        Some(true) => parse_exact_decimal(atom).or_else(|| to_exact(&n)),
+        Some(false) => to_f64(&n).map(Value::Float),
+        None => Some(n),
+    }
+}
+
+fn parse_real(atom: &str, radix: u32) -> Option<Value> {
+    if let Some((numer, denom)) = atom.split_once('/') {
+        let numer = parse_integer(numer, radix)?;
+        if denom.starts_with(['+', '-']) {
+            return None;
+        }
+        let denom = parse_integer(denom, radix)?;
+        if denom.is_zero() {
+            return None;
+        }
+        return Some(rational(BigRational::new(numer, denom)));
+    }
+    if let Some(n) = parse_integer(atom, radix) {
+        return Some(integer(n));
+    }
+    if radix == 10 && looks_numeric(atom) {
+        return atom.parse::<f64>().ok().map(Value::Float);
+    }
+    None
+}
+
+/// Reads a decimal like `0.1` as the exact fraction it's written as, rather than as the nearest
+/// float.
+fn parse_exact_decimal(atom: &str) -> Option<Value> {
+    let (whole, fraction) = atom.split_once('.')?;
+    let digits = parse_integer(&format!("{}{}", whole, fraction), 10)?;
+    let scale = BigInt::from(10).pow(fraction.len() as u32);
+    Some(rational(BigRational::new(digits, scale)))
+}
+
+fn parse_integer(s: &str, radix: u32) -> Option<BigInt> {
+    let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let n = BigInt::from_str_radix(digits, radix).ok()?;
+    Some(if s.starts_with('-') { -n } else { n })
+}
+
+// Rust happily parses "inf" or "nan" as floats, which are valid Scheme symbols.
+fn looks_numeric(atom: &str) -> bool {
+    let digits = atom.trim_start_matches(['+', '-']).trim_start_matches('.');
+    digits.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Returns whether `v` is an integer, exact or not.
+pub fn is_integer(v: &Value) -> bool {
+    exact_integer(v).is_some()
+}
+
+/// Converts an integer, exact or not, to an exact one.
+fn exact_integer(v: &Value) -> Option<BigInt> {
+    match v {
+        Value::Float(n) if n.fract() == 0.0 => BigInt::from_f64(*n),
+        _ => to_big(v),
+    }
+}
+
+/// The sign of a number, or `None` for NaN and non-numbers.
+pub fn sign(v: &Value) -> Option<Ordering> {
+    match v {
+        Value::BigInteger(n) => Some(n.sign().cmp(&Sign::NoSign)),
+        Value::Rational(q) => Some(q.numer().sign().cmp(&Sign::NoSign)),
+        _ => compare(v, &Value::Integer(0)),
+    }
+}
+
+/// Applies an operation on integers. Inexact integers are operated on exactly, but the result is
+/// inexact if either operand is.
+fn integer_op(a: &Value, b: &Value, op: fn(&BigInt, &BigInt) -> BigInt) -> Option<Value> {
+    let res = integer(op(&exact_integer(a)?, &exact_integer(b)?));
+    if is_exact(a) && is_exact(b) {
+        Some(res)
+    } else {
+        to_f64(&res).map(Value::Float)
+    }
+}
+
+/// The quotient of truncating division of the integers `a` and `b`, which must not be zero.
+pub fn quotient(a: &Value, b: &Value) -> Option<Value> {
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        if let Some(n) = x.checked_div(*y) {
+            return Some(Value::Integer(n));
+        }
+    }
+    integer_op(a, b, |x, y| x / y)
+}
+
+/// The remainder of truncating division of the integers `a` and `b`, which has the sign of `a`.
+pub fn remainder(a: &Value, b: &Value) -> Option<Value> {
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        if let Some(n) = x.checked_rem(*y) {
+            return Some(Value::Integer(n));
+        }
+    }
+    integer_op(a, b, |x, y| x % y)
+}
+
+/// The remainder of flooring division of the integers `a` and `b`, which has the sign of `b`.
+pub fn modulo(a: &Value, b: &Value) -> Option<Value> {
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        if let Some(n) = x.checked_rem(*y) {
+            return Some(Value::Integer(if n != 0 && (n < 0) != (*y < 0) {
+                n + y
+            } else {
+                n
+            }));
+        }
+    }
+    integer_op(a, b, |x, y| x.mod_floor(y))
+}
+
+pub fn gcd(a: &Value, b: &Value) -> Option<Value> {
+    integer_op(a, b, |x, y| x.gcd(y))
+}
+
+pub fn lcm(a: &Value, b: &Value) -> Option<Value> {
+    integer_op(a, b, |x, y| x.lcm(y))
+}
+
+/// Returns whether the integer `v` is even.
+pub fn is_even(v: &Value) -> Option<bool> {
+    match v {
+        Value::Integer(n) => Some(n % 2 == 0),
+        _ => exact_integer(v).map(|n| n.is_even()),
+    }
+}
+
+/// The size in bits of the largest exact power [`expt`] computes. Larger ones would take minutes
+/// or exhaust memory.
+pub const MAX_EXPT_BITS: u64 = 1 << 20;
+
+/// Raises `base` to the power `exponent`. An exact base raised to an exact integer gives an exact
+/// result, anything else is computed with floats. A zero base can't be raised to a negative
+/// power exactly. Returns `None` if either isn't a number, or if the exact result would be
+/// larger than [`MAX_EXPT_BITS`].
+pub fn expt(base: &Value, exponent: &Value) -> Option<Value> {
+    if !is_number(base) || !is_number(exponent) {
+        return None;
+    }
+    if is_exact(base) && !matches!(exponent, Value::Rational(_)) && is_exact(exponent) {
+        let (q, e) = (to_rational(base)?, to_big(exponent)?);
+        // 0, 1 and -1 stay small whatever the exponent
+        if q.is_zero() {
+            return Some(Value::Integer(if e.is_zero() { 1 } else { 0 }));
+        }
+        if q.denom().is_one() && q.numer().magnitude().is_one() {
+            return Some(if e.is_even() {
+                Value::Integer(1)
+            } else {
+                rational(q)
+            });
+        }
+        let bits = q.numer().bits().max(q.denom().bits());
+        let e = e
+            .to_i32()
+            .filter(|e| bits.saturating_mul(e.unsigned_abs() as u64) <= MAX_EXPT_BITS)?;
+        return Some(rational(q.pow(e)));
+    }
+    Some(Value::Float(to_f64(base)?.powf(to_f64(exponent)?)))
+}
+
+/// Returns `s` and `r` such that `n = s^2 + r` for the largest such `s`. `n` must be a
+/// non-negative exact integer.
+pub fn exact_integer_sqrt(n: &Value) -> Option<(Value, Value)> {
+    let n = to_big(n)?;
+    let s = n.sqrt();
+    let r = &n - &s * &s;
+    Some((integer(s), integer(r)))
+}
+
+/// The square root of a non-negative number, exact when `v` is the square of an exact number.
+pub fn sqrt(v: &Value) -> Option<Value> {
+    if let Some(q) = to_rational(v) {
+        let (numer, denom) = (q.numer().sqrt(), q.denom().sqrt());
+        if &numer * &numer == *q.numer() && &denom * &denom == *q.denom() {
+            return Some(rational(BigRational::new(numer, denom)));
+        }
+    }
+    to_f64(v).map(|n| Value::Float(n.sqrt()))
+}
+
+/// How [`round`] picks an integer.
+#[derive(Clone, Copy)]
+pub enum Rounding {
+    Floor,
+    Ceiling,
+    /// To the nearest integer, or the even one of two equally near.
+    Round,
+    Truncate,
+}
+
+/// Rounds a number to an integer, keeping its exactness.
+pub fn round(v: &Value, mode: Rounding) -> Option<Value> {
+    match v {
+        Value::Integer(_) | Value::BigInteger(_) => Some(v.clone()),
+        Value::Rational(q) => {
+            let rounded = match mode {
+                Rounding::Floor => q.floor(),
+                Rounding::Ceiling => q.ceil(),
+                Rounding::Truncate => q.trunc(),
+                Rounding::Round => {
+                    let floor = q.floor();
+                    let half = BigRational::new(1.into(), 2.into());
+                    match (q.as_ref() - &floor).cmp(&half) {
+                        Ordering::Less => floor,
+                        Ordering::Greater => floor + BigRational::one(),
+                        Ordering::Equal if floor.to_integer().is_even() => floor,
+                        Ordering::Equal => floor + BigRational::one(),
+                    }
+                }
+            };
+            Some(integer(rounded.to_integer()))
+        }
+        Value::Float(n) => Some(Value::Float(match mode {
+            Rounding::Floor => n.floor(),
+            Rounding::Ceiling => n.ceil(),
+            Rounding::Round => n.round_ties_even(),
+            Rounding::Truncate => n.trunc(),
+        })),
+        _ => None,
+    }
+}
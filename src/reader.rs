@@ -0,0 +1,515 @@
+use core::fmt;
+use std::cell::RefCell;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+use std::vec::IntoIter;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+use crate::error::{Error, Span};
+use crate::macros::Renamed;
+use crate::numbers::{format_float, parse_number};
+use crate::types::{Symbol, Value};
+
+/// A lexical token of Scheme source code.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    LeftParen,
+    RightParen,
+    VectorOpen,
+    BytevectorOpen,
+    Quote,
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+    Dot,
+    String(String),
+    Char(char),
+    Atom(String),
+}
+
+/// A datum produced by the reader: the tree representation of source code.
+#[derive(Clone, Debug)]
+pub enum Datum {
+    Boolean(bool),
+    Integer(i64),
+    BigInteger(Rc<BigInt>),
+    Rational(Rc<BigRational>),
+    Float(f64),
+    String(Rc<str>),
+    Char(char),
+    Symbol(Symbol),
+    /// An identifier introduced by a macro expansion.
+    Renamed(Rc<Renamed>),
+    /// A proper list, along with where it starts in the source.
+    List(Rc<[Datum]>, Span),
+    DottedList(Rc<[Datum]>, Rc<Datum>),
+    Vector(Rc<[Datum]>),
+    Bytevector(Rc<[u8]>),
+}
+
+impl Datum {
+    /// Returns the name an identifier is bound under: the symbol's own name, or the unique name
+    /// of an identifier introduced by a macro.
+    pub fn as_symbol(&self) -> Option<&str> {
+        match self {
+            Datum::Symbol(s) => Some(s.as_str()),
+            Datum::Renamed(renamed) => Some(&renamed.name),
+            _ => None,
+        }
+    }
+
+    /// Converts the datum into the value it denotes when quoted. Identifiers renamed by a macro
+    /// expansion become their original symbols again.
+    pub fn to_value(&self) -> Value {
+        match self {
+            Datum::Boolean(b) => Value::Boolean(*b),
+            Datum::Integer(n) => Value::Integer(*n),
+            Datum::BigInteger(n) => Value::BigInteger(n.clone()),
+            Datum::Rational(q) => Value::Rational(q.clone()),
+            Datum::Float(n) => Value::Float(*n),
+            Datum::String(s) => Value::String(s.to_string()),
+            Datum::Char(c) => Value::Char(*c),
+            Datum::Symbol(s) => Value::Symbol(s.clone()),
+            Datum::Renamed(renamed) => renamed.symbol.to_value(),
+            Datum::List(items, _) => list_to_value(items, Value::Null),
+            Datum::DottedList(items, tail) => list_to_value(items, tail.to_value()),
+            Datum::Vector(items) => Value::vector(items.iter().map(Datum::to_value).collect()),
+            Datum::Bytevector(bytes) => Value::Bytevector(Rc::new(RefCell::new(bytes.to_vec()))),
+        }
+    }
+
+    /// Returns the symbol an identifier was written as in the source, looking through any
+    /// renaming done by macro expansion. Special forms are recognized by this name.
+    pub fn keyword(&self) -> Option<&str> {
+        match self {
+            Datum::Symbol(s) => Some(s.as_str()),
+            Datum::Renamed(renamed) => renamed.symbol.keyword(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Datum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Datum::Boolean(true) => write!(f, "#t"),
+            Datum::Boolean(false) => write!(f, "#f"),
+            Datum::Integer(n) => write!(f, "{}", n),
+            Datum::BigInteger(n) => write!(f, "{}", n),
+            Datum::Rational(q) => write!(f, "{}", q),
+            Datum::Float(n) => write!(f, "{}", format_float(*n)),
+            Datum::String(s) => write!(f, "\"{}\"", s),
+            Datum::Char(c) => write!(f, "{}", Value::Char(*c)),
+            Datum::Symbol(s) => write!(f, "{}", s),
+            Datum::Renamed(renamed) => write!(f, "{}", renamed.symbol),
+            Datum::List(items, _) => {
+                write!(f, "(")?;
+                write_items(f, items)?;
+                write!(f, ")")
+            }
+            Datum::DottedList(items, tail) => {
+                write!(f, "(")?;
+                write_items(f, items)?;
+                write!(f, " . {})", tail)
+            }
+            Datum::Vector(items) => {
+                write!(f, "#(")?;
+                write_items(f, items)?;
+                write!(f, ")")
+            }
+            Datum::Bytevector(bytes) => {
+                write!(f, "#u8(")?;
+                for (i, byte) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", byte)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+fn list_to_value(items: &[Datum], tail: Value) -> Value {
+    items
+        .iter()
+        .rev()
+        .fold(tail, |list, item| Value::cons(item.to_value(), list))
+}
+
+fn write_items(f: &mut fmt::Formatter, items: &[Datum]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Splits source code into tokens, skipping whitespace and `;` comments. Every token is paired
+/// with the position it starts at.
+pub fn tokenize(src: &str, file: Option<Rc<str>>) -> Result<Vec<(Token, Span)>, Error> {
+    let mut lexer = Lexer {
+        chars: src.chars().peekable(),
+        file,
+        line: 1,
+        column: 1,
+    };
+    let mut tokens = Vec::new();
+
+    while let Some(c) = lexer.peek() {
+        let span = lexer.span();
+        let token = match c {
+            c if c.is_whitespace() => {
+                lexer.next();
+                continue;
+            }
+            ';' => {
+                while let Some(c) = lexer.next() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            '(' | '[' => {
+                lexer.next();
+                Token::LeftParen
+            }
+            ')' | ']' => {
+                lexer.next();
+                Token::RightParen
+            }
+            '\'' => {
+                lexer.next();
+                Token::Quote
+            }
+            '`' => {
+                lexer.next();
+                Token::Quasiquote
+            }
+            ',' => {
+                lexer.next();
+                if lexer.peek() == Some('@') {
+                    lexer.next();
+                    Token::UnquoteSplicing
+                } else {
+                    Token::Unquote
+                }
+            }
+            '"' => {
+                lexer.next();
+                let mut s = String::new();
+                loop {
+                    match lexer.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escape_span = lexer.span();
+                            if let Some(c) = read_escape(&mut lexer, escape_span)? {
+                                s.push(c);
+                            }
+                        }
+                        Some(c) => s.push(c),
+                        None => return Err(read_error("unterminated string literal", span)),
+                    }
+                }
+                Token::String(s)
+            }
+            _ => {
+                let mut atom = String::new();
+                while let Some(c) = lexer.peek() {
+                    // The character right after #\ is part of the literal even if it's a
+                    // delimiter, as in #\(
+                    if atom != "#\\" && (c.is_whitespace() || "()[]'`,\";".contains(c)) {
+                        break;
+                    }
+                    atom.push(c);
+                    lexer.next();
+                }
+                if let Some(name) = atom.strip_prefix("#\\") {
+                    match parse_char(name) {
+                        Some(c) => Token::Char(c),
+                        None => return Err(read_error("unknown character name", span)),
+                    }
+                } else if atom == "#" && lexer.peek() == Some('(') {
+                    lexer.next();
+                    Token::VectorOpen
+                } else if atom == "#u8" && lexer.peek() == Some('(') {
+                    lexer.next();
+                    Token::BytevectorOpen
+                } else if atom == "." {
+                    Token::Dot
+                } else {
+                    Token::Atom(atom)
+                }
+            }
+        };
+        tokens.push((token, span));
+    }
+
+    Ok(tokens)
+}
+
+/// Parses what follows `#\` in a character literal: the character itself, its name, or its
+/// hexadecimal code as in `#\x3bb`.
+fn parse_char(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => return Some(c),
+        (None, _) => return None,
+        _ => (),
+    }
+    let named = match name {
+        "alarm" => '\x07',
+        "backspace" => '\x08',
+        "delete" => '\x7f',
+        "escape" => '\x1b',
+        "newline" => '\n',
+        "null" | "nul" => '\0',
+        "return" => '\r',
+        "space" => ' ',
+        "tab" => '\t',
+        _ => {
+            let hex = name.strip_prefix(['x', 'X'])?;
+            return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+        }
+    };
+    Some(named)
+}
+
+/// Reads the escape sequence after a backslash in a string literal. Line continuations, a
+/// backslash followed by a line break, produce no character.
+fn read_escape(lexer: &mut Lexer, span: Span) -> Result<Option<char>, Error> {
+    let c = match lexer.next() {
+        Some(c) => c,
+        None => return Err(read_error("unterminated string literal", span)),
+    };
+    let escaped = match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        'a' => '\x07',
+        'b' => '\x08',
+        '0' => '\0',
+        '\\' | '"' | '|' => c,
+        'x' | 'X' => {
+            let mut hex = String::new();
+            loop {
+                match lexer.next() {
+                    Some(';') => break,
+                    Some(c) if c.is_ascii_hexdigit() => hex.push(c),
+                    _ => return Err(read_error("expected ';' after hex escape", span)),
+                }
+            }
+            match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                Some(c) => c,
+                None => return Err(read_error("invalid hex escape", span)),
+            }
+        }
+        c if c.is_whitespace() => {
+            // \<intraline whitespace>*<line ending><intraline whitespace>*
+            let mut c = c;
+            while c != '\n' {
+                match lexer.next() {
+                    Some(next) if next.is_whitespace() => c = next,
+                    _ => return Err(read_error("invalid line continuation", span)),
+                }
+            }
+            while lexer.peek().is_some_and(|c| c == ' ' || c == '\t') {
+                lexer.next();
+            }
+            return Ok(None);
+        }
+        _ => return Err(read_error("unknown escape sequence", span)),
+    };
+    Ok(Some(escaped))
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    file: Option<Rc<str>>,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn span(&self) -> Span {
+        Span {
+            file: self.file.clone(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+fn read_error(message: &str, span: Span) -> Error {
+    Error::Read {
+        message: message.to_string(),
+        span,
+    }
+}
+
+type Tokens = Peekable<IntoIter<(Token, Span)>>;
+
+/// Reads every datum in `src`.
+pub fn read(src: &str) -> Result<Vec<Datum>, Error> {
+    read_source(src, None)
+}
+
+/// Reads every datum in `src`, attributing positions to `file`.
+pub fn read_source(src: &str, file: Option<&str>) -> Result<Vec<Datum>, Error> {
+    let end = Span {
+        file: file.map(Rc::from),
+        line: src.lines().count().max(1),
+        column: src
+            .lines()
+            .last()
+            .map_or(1, |line| line.chars().count() + 1),
+    };
+    let mut tokens = tokenize(src, end.file.clone())?.into_iter().peekable();
+    let mut res = Vec::new();
+    while tokens.peek().is_some() {
+        res.push(read_datum(&mut tokens, &end)?);
+    }
+    Ok(res)
+}
+
+fn read_datum(tokens: &mut Tokens, end: &Span) -> Result<Datum, Error> {
+    let (token, span) = match tokens.next() {
+        Some(next) => next,
+        None => return Err(read_error("unexpected end of input", end.clone())),
+    };
+    match token {
+        Token::LeftParen => read_list(tokens, span, end),
+        Token::VectorOpen => {
+            let items = read_vector(tokens, span, end)?;
+            Ok(Datum::Vector(
+                items.into_iter().map(|(item, _)| item).collect(),
+            ))
+        }
+        Token::BytevectorOpen => {
+            let mut bytes = Vec::new();
+            for (item, item_span) in read_vector(tokens, span, end)? {
+                match item {
+                    Datum::Integer(n @ 0..=255) => bytes.push(n as u8),
+                    _ => return Err(read_error("bytevector elements must be bytes", item_span)),
+                }
+            }
+            Ok(Datum::Bytevector(bytes.into()))
+        }
+        // 'x, `x, ,x and ,@x are abbreviations for (quote x), (quasiquote x), (unquote x) and
+        // (unquote-splicing x)
+        Token::Quote | Token::Quasiquote | Token::Unquote | Token::UnquoteSplicing => {
+            let keyword = match token {
+                Token::Quote => "quote",
+                Token::Quasiquote => "quasiquote",
+                Token::Unquote => "unquote",
+                _ => "unquote-splicing",
+            };
+            let quoted = read_datum(tokens, end)?;
+            Ok(Datum::List(
+                vec![Datum::Symbol(Symbol::new(keyword)), quoted].into(),
+                span,
+            ))
+        }
+        Token::String(s) => Ok(Datum::String(s.into())),
+        Token::Char(c) => Ok(Datum::Char(c)),
+        Token::Atom(atom) => Ok(parse_atom(&atom)),
+        Token::RightParen => Err(read_error("unexpected ')'", span)),
+        Token::Dot => Err(read_error("unexpected '.'", span)),
+    }
+}
+
+/// Reads the elements of a vector or bytevector literal up to the closing parenthesis. Each element
+/// is paired with where it starts.
+fn read_vector(tokens: &mut Tokens, span: Span, end: &Span) -> Result<Vec<(Datum, Span)>, Error> {
+    let mut items = Vec::new();
+    loop {
+        match tokens.peek() {
+            Some((Token::RightParen, _)) => {
+                tokens.next();
+                return Ok(items);
+            }
+            Some((Token::Dot, span)) => {
+                return Err(read_error("unexpected '.' in vector", span.clone()))
+            }
+            Some((_, item_span)) => {
+                let item_span = item_span.clone();
+                items.push((read_datum(tokens, end)?, item_span));
+            }
+            None => return Err(read_error("missing ')'", span)),
+        }
+    }
+}
+
+fn read_list(tokens: &mut Tokens, span: Span, end: &Span) -> Result<Datum, Error> {
+    let mut items = Vec::new();
+    loop {
+        match tokens.peek() {
+            Some((Token::RightParen, _)) => {
+                tokens.next();
+                return Ok(Datum::List(items.into(), span));
+            }
+            Some((Token::Dot, dot_span)) => {
+                let dot_span = dot_span.clone();
+                tokens.next();
+                if items.is_empty() {
+                    return Err(read_error("unexpected '.'", dot_span));
+                }
+                let tail = read_datum(tokens, end)?;
+                match tokens.next() {
+                    Some((Token::RightParen, _)) => (),
+                    _ => return Err(read_error("expected ')' after dotted tail", dot_span)),
+                }
+                return Ok(match tail {
+                    Datum::List(rest, _) => {
+                        items.extend(rest.iter().cloned());
+                        Datum::List(items.into(), span)
+                    }
+                    Datum::DottedList(rest, tail) => {
+                        items.extend(rest.iter().cloned());
+                        Datum::DottedList(items.into(), tail)
+                    }
+                    tail => Datum::DottedList(items.into(), Rc::new(tail)),
+                });
+            }
+            Some(_) => items.push(read_datum(tokens, end)?),
+            None => return Err(read_error("missing ')'", span)),
+        }
+    }
+}
+
+fn parse_atom(atom: &str) -> Datum {
+    match atom {
+        "#t" | "#true" => return Datum::Boolean(true),
+        "#f" | "#false" => return Datum::Boolean(false),
+        _ => (),
+    }
+    match parse_number(atom, 10) {
+        Some(Value::Integer(n)) => Datum::Integer(n),
+        Some(Value::BigInteger(n)) => Datum::BigInteger(n),
+        Some(Value::Rational(q)) => Datum::Rational(q),
+        Some(Value::Float(n)) => Datum::Float(n),
+        _ => Datum::Symbol(Symbol::new(atom)),
+    }
+}
@@ -0,0 +1,186 @@
+use std::cmp::Ordering;
+
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::numbers::{is_number, number_to_string, parse_number};
+use crate::types::Arity;
+use crate::types::Value;
+
+/// Binds the string procedures in `env`.
+pub fn add_string_procedures(env: &Environment) {
+    env.define_primitive("string?", Arity::Exactly(1), |args| {
+        Ok(Value::Boolean(matches!(args[0], Value::String(_))))
+    });
+    env.define_primitive("string-length", Arity::Exactly(1), |args| {
+        let s = string_arg("string-length", &args[0])?;
+        Ok(Value::Integer(s.chars().count() as i64))
+    });
+    env.define_primitive("string-ref", Arity::Exactly(2), |args| {
+        let s = string_arg("string-ref", &args[0])?;
+        let k = index_arg("string-ref", &args[1])?;
+        match s.chars().nth(k) {
+            Some(c) => Ok(Value::Char(c)),
+            None => Err(Error::wrong_type("string-ref", "index in range", &args[1])),
+        }
+    });
+    env.define_primitive("substring", Arity::Between(2, 3), |args| {
+        let s = string_arg("substring", &args[0])?;
+        let len = s.chars().count();
+        let start = index_arg("substring", &args[1])?;
+        let end = match args.get(2) {
+            Some(end) => index_arg("substring", end)?,
+            None => len,
+        };
+        if end > len {
+            return Err(Error::wrong_type("substring", "index in range", &args[2]));
+        }
+        if start > end {
+            return Err(Error::wrong_type("substring", "index in range", &args[1]));
+        }
+        Ok(Value::String(
+            s.chars().skip(start).take(end - start).collect(),
+        ))
+    });
+    env.define_primitive("string-append", Arity::AtLeast(0), |args| {
+        let mut res = String::new();
+        for arg in &args {
+            res.push_str(string_arg("string-append", arg)?);
+        }
+        Ok(Value::String(res))
+    });
+    env.define_primitive("string-upcase", Arity::Exactly(1), |args| {
+        Ok(Value::String(
+            string_arg("string-upcase", &args[0])?.to_uppercase(),
+        ))
+    });
+    env.define_primitive("string-downcase", Arity::Exactly(1), |args| {
+        Ok(Value::String(
+            string_arg("string-downcase", &args[0])?.to_lowercase(),
+        ))
+    });
+    env.define_primitive("string-index", Arity::Exactly(2), |args| {
+        let s = string_arg("string-index", &args[0])?;
+        let needle = char_arg("string-index", &args[1])?;
+        match s.chars().position(|c| c == needle) {
+            Some(i) => Ok(Value::Integer(i as i64)),
+            None => Ok(Value::Boolean(false)),
+        }
+    });
+    env.define_primitive("string-split", Arity::Exactly(2), |args| {
+        let s = string_arg("string-split", &args[0])?;
+        let delimiter = char_arg("string-split", &args[1])?;
+        Ok(s.split(delimiter).rev().fold(Value::Null, |list, part| {
+            Value::cons(Value::String(part.to_string()), list)
+        }))
+    });
+    env.define_primitive("string->number", Arity::Between(1, 2), |args| {
+        let s = string_arg("string->number", &args[0])?;
+        let radix = radix_arg("string->number", args.get(1))?;
+        Ok(parse_number(s, radix).unwrap_or(Value::Boolean(false)))
+    });
+    env.define_primitive("number->string", Arity::Between(1, 2), |args| {
+        let radix = radix_arg("number->string", args.get(1))?;
+        match number_to_string(&args[0], radix) {
+            Some(s) => Ok(Value::String(s)),
+            None if is_number(&args[0]) => Err(Error::wrong_type(
+                "number->string",
+                "exact number",
+                &args[0],
+            )),
+            None => Err(Error::wrong_type("number->string", "number", &args[0])),
+        }
+    });
+    env.define_primitive("string->list", Arity::Exactly(1), |args| {
+        let s = string_arg("string->list", &args[0])?;
+        Ok(s.chars()
+            .rev()
+            .fold(Value::Null, |list, c| Value::cons(Value::Char(c), list)))
+    });
+    env.define_primitive("list->string", Arity::Exactly(1), |args| {
+        let mut s = String::new();
+        let mut list = args[0].clone();
+        while let Value::Pair(pair) = list {
+            s.push(char_arg("list->string", &pair.car())?);
+            list = pair.cdr();
+        }
+        match list {
+            Value::Null => Ok(Value::String(s)),
+            _ => Err(Error::wrong_type("list->string", "list", &args[0])),
+        }
+    });
+    env.define_primitive("string", Arity::AtLeast(0), |args| {
+        let chars = args.iter().map(|arg| char_arg("string", arg));
+        Ok(Value::String(chars.collect::<Result<_, _>>()?))
+    });
+    env.define_primitive(
+        "string=?",
+        Arity::AtLeast(1),
+        compare_strings("string=?", |o| o == Ordering::Equal),
+    );
+    env.define_primitive(
+        "string<?",
+        Arity::AtLeast(1),
+        compare_strings("string<?", |o| o == Ordering::Less),
+    );
+    env.define_primitive(
+        "string>?",
+        Arity::AtLeast(1),
+        compare_strings("string>?", |o| o == Ordering::Greater),
+    );
+    env.define_primitive(
+        "string<=?",
+        Arity::AtLeast(1),
+        compare_strings("string<=?", |o| o != Ordering::Greater),
+    );
+    env.define_primitive(
+        "string>=?",
+        Arity::AtLeast(1),
+        compare_strings("string>=?", |o| o != Ordering::Less),
+    );
+}
+
+/// Builds a comparison that holds when `holds` accepts the ordering of every pair of adjacent
+/// arguments.
+fn compare_strings(
+    name: &'static str,
+    holds: fn(Ordering) -> bool,
+) -> impl Fn(Vec<Value>) -> Result<Value, Error> {
+    move |args| {
+        let strings = args
+            .iter()
+            .map(|arg| string_arg(name, arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::Boolean(
+            strings.windows(2).all(|pair| holds(pair[0].cmp(pair[1]))),
+        ))
+    }
+}
+
+fn string_arg<'a>(proc: &str, arg: &'a Value) -> Result<&'a str, Error> {
+    match arg {
+        Value::String(s) => Ok(s),
+        _ => Err(Error::wrong_type(proc, "string", arg)),
+    }
+}
+
+pub fn char_arg(proc: &str, arg: &Value) -> Result<char, Error> {
+    match arg {
+        Value::Char(c) => Ok(*c),
+        _ => Err(Error::wrong_type(proc, "character", arg)),
+    }
+}
+
+pub fn index_arg(proc: &str, arg: &Value) -> Result<usize, Error> {
+    match arg {
+        Value::Integer(n) if *n >= 0 => Ok(*n as usize),
+        _ => Err(Error::wrong_type(proc, "non-negative integer", arg)),
+    }
+}
+
+fn radix_arg(proc: &str, arg: Option<&Value>) -> Result<u32, Error> {
+    match arg {
+        None => Ok(10),
+        Some(Value::Integer(radix @ (2 | 8 | 10 | 16))) => Ok(*radix as u32),
+        Some(arg) => Err(Error::wrong_type(proc, "radix 2, 8, 10 or 16", arg)),
+    }
+}
@@ -1,120 +1,489 @@
 use core::fmt;
-use std::{cmp::Ordering, rc::Rc};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::gc;
+use crate::macros::Macro;
+use crate::numbers::format_float;
+use crate::reader::Datum;
+use crate::vm::Closure;
 
 #[derive(Clone, Debug)]
 pub enum Value {
     Integer(i64),
+    BigInteger(Rc<BigInt>),
+    Rational(Rc<BigRational>),
     Float(f64),
     String(String),
+    Char(char),
     Boolean(bool),
-    Pair(Box<Value>, Box<Value>),
-    Procedure(Vec<String>, Vec<String>),
+    Symbol(Symbol),
+    Pair(Rc<Pair>),
+    Vector(Rc<RefCell<Vec<Value>>>),
+    Bytevector(Rc<RefCell<Vec<u8>>>),
+    Procedure(Rc<Procedure>),
+    CaseLambda(Rc<CaseLambda>),
+    Primitive(Rc<Primitive>),
+    /// A procedure compiled to bytecode.
+    Closure(Rc<Closure>),
+    Macro(Rc<Macro>),
     Null,
 }
 
+/// An interned symbol. Every symbol with the same name shares one allocation, so symbols are
+/// compared by pointer.
+#[derive(Clone)]
+pub struct Symbol(Rc<str>);
+
+thread_local! {
+    static SYMBOLS: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+impl Symbol {
+    pub fn new(name: &str) -> Self {
+        SYMBOLS.with(|symbols| {
+            let mut symbols = symbols.borrow_mut();
+            match symbols.get(name) {
+                Some(interned) => Symbol(interned.clone()),
+                None => {
+                    let interned: Rc<str> = name.into();
+                    symbols.insert(interned.clone());
+                    Symbol(interned)
+                }
+            }
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&self.0).hash(state);
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A mutable cons cell. Pairs are shared between every value referencing them, so mutations
+/// through `set-car!` and `set-cdr!` are visible everywhere.
+#[derive(Debug)]
+pub struct Pair {
+    pub car: RefCell<Value>,
+    pub cdr: RefCell<Value>,
+}
+
+impl Pair {
+    pub fn car(&self) -> Value {
+        self.car.borrow().clone()
+    }
+
+    pub fn cdr(&self) -> Value {
+        self.cdr.borrow().clone()
+    }
+}
+
+impl Drop for Pair {
+    /// Frees the rest of a list in a loop rather than recursively, so dropping a long list can't
+    /// overflow the stack.
+    fn drop(&mut self) {
+        let mut rest = self.cdr.replace(Value::Null);
+        while let Value::Pair(pair) = rest {
+            match Rc::try_unwrap(pair) {
+                Ok(pair) => rest = pair.cdr.replace(Value::Null),
+                // Still referenced elsewhere
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl Value {
+    pub fn cons(car: Value, cdr: Value) -> Value {
+        let pair = Rc::new(Pair {
+            car: RefCell::new(car),
+            cdr: RefCell::new(cdr),
+        });
+        gc::track(&pair);
+        Value::Pair(pair)
+    }
+
+    pub fn vector(items: Vec<Value>) -> Value {
+        let vector = Rc::new(RefCell::new(items));
+        gc::track(&vector);
+        Value::Vector(vector)
+    }
+
+    pub fn procedure(proc: Procedure) -> Value {
+        let proc = Rc::new(proc);
+        gc::track(&proc);
+        Value::Procedure(proc)
+    }
+
+    pub fn case_lambda(proc: CaseLambda) -> Value {
+        let proc = Rc::new(proc);
+        gc::track(&proc);
+        Value::CaseLambda(proc)
+    }
+
+    /// Wraps a macro, which can only be bound by `define-syntax` and its relatives.
+    pub fn syntax(mac: Macro) -> Value {
+        let mac = Rc::new(mac);
+        gc::track(&mac);
+        Value::Macro(mac)
+    }
+
+    /// Whether the value counts as true in a conditional: everything but `#f` does.
+    pub fn is_true(&self) -> bool {
+        !matches!(self, Value::Boolean(false))
+    }
+}
+
+/// The number of arguments a procedure accepts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+    Between(usize, usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, given: usize) -> bool {
+        match *self {
+            Arity::Exactly(n) => given == n,
+            Arity::AtLeast(n) => given >= n,
+            Arity::Between(min, max) => min <= given && given <= max,
+        }
+    }
+
+    /// Fails with an arity error on behalf of `procedure` unless `given` arguments are accepted.
+    pub fn check(&self, procedure: &str, given: usize) -> Result<(), Error> {
+        if self.accepts(given) {
+            Ok(())
+        } else {
+            Err(Error::arity(procedure, *self, given))
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arity::Exactly(n) => write!(f, "{}", n),
+            Arity::AtLeast(n) => write!(f, "at least {}", n),
+            Arity::Between(min, max) => write!(f, "between {} and {}", min, max),
+        }
+    }
+}
+
+/// A user-defined procedure, closing over the environment it was created in.
+pub struct Procedure {
+    pub name: Option<String>,
+    pub params: Vec<String>,
+    /// The parameter bound to the list of the arguments after `params`, if the procedure takes
+    /// any number of them.
+    pub rest: Option<String>,
+    pub body: Rc<[Datum]>,
+    pub env: Rc<Environment>,
+}
+
+impl Procedure {
+    /// The name the procedure was defined with, for error messages.
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("#<procedure>")
+    }
+
+    pub fn arity(&self) -> Arity {
+        match self.rest {
+            Some(_) => Arity::AtLeast(self.params.len()),
+            None => Arity::Exactly(self.params.len()),
+        }
+    }
+}
+
+/// A procedure made by `case-lambda`. Calling it runs the first clause that accepts the number of
+/// arguments given.
+#[derive(Debug)]
+pub struct CaseLambda {
+    pub clauses: Vec<Procedure>,
+}
+
+impl CaseLambda {
+    /// The range of argument counts accepted by any of the clauses.
+    pub fn arity(&self) -> Arity {
+        let min = self
+            .clauses
+            .iter()
+            .map(|c| c.params.len())
+            .min()
+            .unwrap_or(0);
+        let max = self
+            .clauses
+            .iter()
+            .map(|c| c.params.len())
+            .max()
+            .unwrap_or(0);
+        if self.clauses.iter().any(|c| c.rest.is_some()) {
+            Arity::AtLeast(min)
+        } else if min == max {
+            Arity::Exactly(min)
+        } else {
+            Arity::Between(min, max)
+        }
+    }
+}
+
+impl fmt::Debug for Procedure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Procedure")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .field("rest", &self.rest)
+            .field("body", &self.body)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A procedure implemented in Rust.
+pub struct Primitive {
+    pub name: String,
+    pub arity: Arity,
+    pub function: PrimitiveProcedure,
+}
+
+impl Primitive {
+    /// Calls the function after checking that it accepts the number of arguments given.
+    pub fn call(&self, args: Vec<Value>) -> Result<Value, Error> {
+        self.arity.check(&self.name, args.len())?;
+        (self.function)(args)
+    }
+
+    /// Whether this is `apply`, whose calls the evaluator and the virtual machine make
+    /// themselves.
+    pub fn is_apply(&self) -> bool {
+        self.name == "apply"
+    }
+}
+
+impl fmt::Debug for Primitive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Primitive")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Value::Integer(n) => write!(f, "{}", n),
-            Value::Float(n) => write!(f, "{}", n),
+            Value::BigInteger(n) => write!(f, "{}", n),
+            Value::Rational(q) => write!(f, "{}", q),
+            Value::Float(n) => write!(f, "{}", format_float(*n)),
             Value::String(s) => write!(f, "{}", s),
+            Value::Char(c) => match char_name(*c) {
+                Some(name) => write!(f, "#\\{}", name),
+                None => write!(f, "#\\{}", c),
+            },
             Value::Boolean(true) => write!(f, "#t"),
             Value::Boolean(false) => write!(f, "#f"),
+            Value::Symbol(s) => write!(f, "{}", s),
+            Value::Vector(items) => {
+                write!(f, "#(")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+            Value::Bytevector(bytes) => {
+                write!(f, "#u8(")?;
+                for (i, byte) in bytes.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", byte)?;
+                }
+                write!(f, ")")
+            }
             Value::Null => write!(f, "()"),
-            Value::Procedure(_, _) => write!(f, ""),
-            Value::Pair(car, cdr) => match **cdr {
-                Value::Pair(_, _) => {
-                    let mut s = String::new();
-                    s.push_str("(");
-                    s.push_str(&format!("{}", car));
-                    let mut current_pair = cdr.clone();
-                    loop {
-                        match *current_pair {
-                            Value::Pair(ref car, ref cdr) => {
-                                s.push_str(&format!(" {}", car));
-                                current_pair = cdr.clone();
-                            }
-                            Value::Null => break,
-                            _ => {
-                                s.push_str(&format!(" . {}", cdr));
-                                break;
-                            }
+            Value::Procedure(proc) => write_procedure(f, proc.name.as_deref()),
+            Value::CaseLambda(_) => write_procedure(f, None),
+            Value::Primitive(primitive) => write_procedure(f, Some(&primitive.name)),
+            Value::Closure(closure) => write_procedure(f, closure.code.name.as_deref()),
+            Value::Macro(mac) => write!(f, "#<macro {}>", mac.name),
+            Value::Pair(pair) => {
+                write!(f, "({}", pair.car())?;
+                let mut rest = pair.cdr();
+                // Stops a circular list once it has come round to where a pointer moving at half
+                // the speed is
+                let mut tortoise = pair.clone();
+                let mut written = 1;
+                loop {
+                    if let Value::Pair(pair) = &rest {
+                        if Rc::ptr_eq(pair, &tortoise) {
+                            write!(f, " ...")?;
+                            break;
                         }
                     }
-                    s.push_str(")");
-                    write!(f, "{}", s)
+                    rest = match rest {
+                        Value::Pair(pair) => {
+                            write!(f, " {}", pair.car())?;
+                            written += 1;
+                            if written % 2 == 0 {
+                                if let Value::Pair(next) = tortoise.cdr() {
+                                    tortoise = next;
+                                }
+                            }
+                            pair.cdr()
+                        }
+                        Value::Null => break,
+                        tail => {
+                            write!(f, " . {}", tail)?;
+                            break;
+                        }
+                    };
                 }
-                _ => write!(f, "({} . {})", car, cdr),
-            },
+                write!(f, ")")
+            }
         }
     }
 }
 
-impl PartialEq for Value {
-    fn eq(&self, other: &Value) -> bool {
-        match (self, other) {
-            (Value::Integer(a), Value::Integer(b)) => a.eq(b),
-            (Value::Integer(a), Value::Float(b)) => (*a as f64).eq(b),
-            (Value::Float(a), Value::Integer(b)) => a.eq(&(*b as f64)),
-            (Value::Float(a), Value::Float(b)) => a.eq(b),
-            (Value::String(a), Value::String(b)) => a.eq(b),
-            (Value::Boolean(a), Value::Boolean(b)) => a.eq(b),
-            (Value::Null, Value::Null) => true,
-            _ => false,
-        }
-    }
-    fn ne(&self, other: &Value) -> bool {
-        !self.eq(other)
+fn write_procedure(f: &mut fmt::Formatter, name: Option<&str>) -> fmt::Result {
+    match name {
+        Some(name) => write!(f, "#<procedure {}>", name),
+        None => write!(f, "#<procedure>"),
     }
 }
 
-impl PartialOrd for Value {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
-            (Value::Integer(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
-            (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
-            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
-            _ => None,
-        }
+/// Returns the name characters without a visible glyph are written with.
+fn char_name(c: char) -> Option<&'static str> {
+    match c {
+        '\x07' => Some("alarm"),
+        '\x08' => Some("backspace"),
+        '\x7f' => Some("delete"),
+        '\x1b' => Some("escape"),
+        '\n' => Some("newline"),
+        '\0' => Some("null"),
+        '\r' => Some("return"),
+        ' ' => Some("space"),
+        '\t' => Some("tab"),
+        _ => None,
     }
+}
 
-    fn lt(&self, other: &Self) -> bool {
-        match self.partial_cmp(other) {
-            Some(Ordering::Less) => true,
-            _ => false,
-        }
-    }
-    fn le(&self, other: &Self) -> bool {
-        match self.partial_cmp(other) {
-            Some(Ordering::Less) | Some(Ordering::Equal) => true,
+impl Value {
+    /// Scheme's `eq?`: whether two values are the same object. Pairs, vectors, bytevectors,
+    /// procedures and bignums are compared by identity, immediate values like fixnums, characters
+    /// and symbols by value. Strings aren't shared, so they are compared by content.
+    pub fn is_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::BigInteger(a), Value::BigInteger(b)) => Rc::ptr_eq(a, b),
+            (Value::Rational(a), Value::Rational(b)) => Rc::ptr_eq(a, b),
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Char(a), Value::Char(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Symbol(a), Value::Symbol(b)) => a == b,
+            (Value::Pair(a), Value::Pair(b)) => Rc::ptr_eq(a, b),
+            (Value::Vector(a), Value::Vector(b)) => Rc::ptr_eq(a, b),
+            (Value::Bytevector(a), Value::Bytevector(b)) => Rc::ptr_eq(a, b),
+            (Value::Procedure(a), Value::Procedure(b)) => Rc::ptr_eq(a, b),
+            (Value::CaseLambda(a), Value::CaseLambda(b)) => Rc::ptr_eq(a, b),
+            (Value::Primitive(a), Value::Primitive(b)) => Rc::ptr_eq(a, b),
+            (Value::Closure(a), Value::Closure(b)) => Rc::ptr_eq(a, b),
+            (Value::Macro(a), Value::Macro(b)) => Rc::ptr_eq(a, b),
+            (Value::Null, Value::Null) => true,
             _ => false,
         }
     }
 
-    fn gt(&self, other: &Self) -> bool {
-        match self.partial_cmp(other) {
-            Some(Ordering::Greater) => true,
-            _ => false,
+    /// Scheme's `eqv?`: like `eq?`, but numbers are equivalent when they have the same
+    /// exactness and are numerically equal.
+    pub fn is_eqv(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::BigInteger(a), Value::BigInteger(b)) => a == b,
+            (Value::Rational(a), Value::Rational(b)) => a == b,
+            _ => self.is_eq(other),
         }
     }
-    fn ge(&self, other: &Self) -> bool {
-        match self.partial_cmp(other) {
-            Some(Ordering::Greater) | Some(Ordering::Equal) => true,
-            _ => false,
+
+    /// Scheme's `equal?`: whether two values have the same structure, comparing pairs, vectors,
+    /// bytevectors and strings by their contents and everything else with `eqv?`.
+    pub fn is_equal(&self, other: &Value) -> bool {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        // Walk down the spine of lists iteratively, so long lists don't overflow the stack
+        loop {
+            match (&a, &b) {
+                (Value::Pair(x), Value::Pair(y)) => {
+                    if Rc::ptr_eq(x, y) {
+                        return true;
+                    }
+                    if !x.car().is_equal(&y.car()) {
+                        return false;
+                    }
+                    let (next_a, next_b) = (x.cdr(), y.cdr());
+                    a = next_a;
+                    b = next_b;
+                }
+                (Value::Vector(x), Value::Vector(y)) => {
+                    let (x, y) = (x.borrow(), y.borrow());
+                    return x.len() == y.len()
+                        && x.iter().zip(y.iter()).all(|(x, y)| x.is_equal(y));
+                }
+                (Value::Bytevector(x), Value::Bytevector(y)) => return *x.borrow() == *y.borrow(),
+                _ => return a.is_eqv(&b),
+            }
         }
     }
 }
 
-pub type PrimitiveProcedure = Rc<dyn Fn(Vec<Value>) -> Result<Option<Value>, &'static str>>;
-
-pub fn check_for_floats(args: &Vec<Value>) -> Result<bool, ()> {
-    for val in args {
-        match val {
-            Value::Integer(_) => (),
-            Value::Float(_) => return Ok(true),
-            _ => return Err(()),
-        }
+/// Values are equal when they are `equal?`.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.is_equal(other)
     }
-    Ok(false)
 }
+
+pub type PrimitiveProcedure = Box<dyn Fn(Vec<Value>) -> Result<Value, Error>>;
@@ -0,0 +1,188 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::eval::apply;
+use crate::strings::index_arg;
+use crate::types::Arity;
+use crate::types::Value;
+
+/// Binds the vector and bytevector procedures in `env`.
+pub fn add_vector_procedures(env: &Environment) {
+    env.define_primitive("vector?", Arity::Exactly(1), |args| {
+        Ok(Value::Boolean(matches!(args[0], Value::Vector(_))))
+    });
+    env.define_primitive("make-vector", Arity::Between(1, 2), |args| {
+        let k = index_arg("make-vector", &args[0])?;
+        let fill = args.get(1).cloned().unwrap_or(Value::Null);
+        Ok(new_vector(vec![fill; k]))
+    });
+    env.define_primitive("vector", Arity::AtLeast(0), |args| Ok(new_vector(args)));
+    env.define_primitive("vector-length", Arity::Exactly(1), |args| {
+        let v = vector_arg("vector-length", &args[0])?;
+        let len = v.borrow().len();
+        Ok(Value::Integer(len as i64))
+    });
+    env.define_primitive("vector-ref", Arity::Exactly(2), |args| {
+        let v = vector_arg("vector-ref", &args[0])?;
+        let k = index_arg("vector-ref", &args[1])?;
+        let item = v.borrow().get(k).cloned();
+        item.ok_or_else(|| Error::wrong_type("vector-ref", "index in range", &args[1]))
+    });
+    env.define_primitive("vector-set!", Arity::Exactly(3), |args| {
+        let v = vector_arg("vector-set!", &args[0])?;
+        let k = index_arg("vector-set!", &args[1])?;
+        match v.borrow_mut().get_mut(k) {
+            Some(item) => *item = args[2].clone(),
+            None => return Err(Error::wrong_type("vector-set!", "index in range", &args[1])),
+        }
+        Ok(Value::Null)
+    });
+    env.define_primitive("vector->list", Arity::Between(1, 3), |args| {
+        let v = vector_arg("vector->list", &args[0])?;
+        let v = v.borrow();
+        let (start, end) = range_args("vector->list", &args[1..], v.len())?;
+        Ok(v[start..end]
+            .iter()
+            .rev()
+            .fold(Value::Null, |list, item| Value::cons(item.clone(), list)))
+    });
+    env.define_primitive("list->vector", Arity::Exactly(1), |args| {
+        let mut items = Vec::new();
+        let mut list = args[0].clone();
+        while let Value::Pair(pair) = list {
+            items.push(pair.car());
+            list = pair.cdr();
+        }
+        match list {
+            Value::Null => Ok(new_vector(items)),
+            _ => Err(Error::wrong_type("list->vector", "list", &args[0])),
+        }
+    });
+    env.define_primitive("vector-fill!", Arity::Between(2, 4), |args| {
+        let v = vector_arg("vector-fill!", &args[0])?;
+        let mut v = v.borrow_mut();
+        let (start, end) = range_args("vector-fill!", &args[2..], v.len())?;
+        v[start..end].fill(args[1].clone());
+        Ok(Value::Null)
+    });
+    // (vector-map proc vector1 vector2 ...) stops at the end of the shortest vector
+    env.define_primitive("vector-map", Arity::AtLeast(2), |args| {
+        let vectors = args[1..]
+            .iter()
+            .map(|arg| Ok(vector_arg("vector-map", arg)?.borrow().clone()))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let len = vectors.iter().map(Vec::len).min().unwrap_or(0);
+        let mut res = Vec::with_capacity(len);
+        for i in 0..len {
+            let call_args = vectors.iter().map(|v| v[i].clone()).collect();
+            res.push(apply(&args[0], call_args)?);
+        }
+        Ok(new_vector(res))
+    });
+    env.define_primitive("bytevector?", Arity::Exactly(1), |args| {
+        Ok(Value::Boolean(matches!(args[0], Value::Bytevector(_))))
+    });
+    env.define_primitive("make-bytevector", Arity::Between(1, 2), |args| {
+        let k = index_arg("make-bytevector", &args[0])?;
+        let fill = match args.get(1) {
+            Some(fill) => byte_arg("make-bytevector", fill)?,
+            None => 0,
+        };
+        Ok(new_bytevector(vec![fill; k]))
+    });
+    env.define_primitive("bytevector", Arity::AtLeast(0), |args| {
+        let bytes = args.iter().map(|arg| byte_arg("bytevector", arg));
+        Ok(new_bytevector(bytes.collect::<Result<_, _>>()?))
+    });
+    env.define_primitive("bytevector-length", Arity::Exactly(1), |args| {
+        let bytes = bytevector_arg("bytevector-length", &args[0])?;
+        let len = bytes.borrow().len();
+        Ok(Value::Integer(len as i64))
+    });
+    env.define_primitive("bytevector-u8-ref", Arity::Exactly(2), |args| {
+        let bytes = bytevector_arg("bytevector-u8-ref", &args[0])?;
+        let k = index_arg("bytevector-u8-ref", &args[1])?;
+        let byte = bytes.borrow().get(k).copied();
+        match byte {
+            Some(byte) => Ok(Value::Integer(byte as i64)),
+            None => Err(Error::wrong_type(
+                "bytevector-u8-ref",
+                "index in range",
+                &args[1],
+            )),
+        }
+    });
+    env.define_primitive("bytevector-u8-set!", Arity::Exactly(3), |args| {
+        let bytes = bytevector_arg("bytevector-u8-set!", &args[0])?;
+        let k = index_arg("bytevector-u8-set!", &args[1])?;
+        let byte = byte_arg("bytevector-u8-set!", &args[2])?;
+        match bytes.borrow_mut().get_mut(k) {
+            Some(item) => *item = byte,
+            None => {
+                return Err(Error::wrong_type(
+                    "bytevector-u8-set!",
+                    "index in range",
+                    &args[1],
+                ))
+            }
+        }
+        Ok(Value::Null)
+    });
+    env.define_primitive("bytevector-copy", Arity::Between(1, 3), |args| {
+        let bytes = bytevector_arg("bytevector-copy", &args[0])?;
+        let bytes = bytes.borrow();
+        let (start, end) = range_args("bytevector-copy", &args[1..], bytes.len())?;
+        Ok(new_bytevector(bytes[start..end].to_vec()))
+    });
+}
+
+fn new_vector(items: Vec<Value>) -> Value {
+    Value::vector(items)
+}
+
+fn new_bytevector(bytes: Vec<u8>) -> Value {
+    Value::Bytevector(Rc::new(RefCell::new(bytes)))
+}
+
+fn vector_arg<'a>(proc: &str, arg: &'a Value) -> Result<&'a RefCell<Vec<Value>>, Error> {
+    match arg {
+        Value::Vector(v) => Ok(v),
+        _ => Err(Error::wrong_type(proc, "vector", arg)),
+    }
+}
+
+fn bytevector_arg<'a>(proc: &str, arg: &'a Value) -> Result<&'a RefCell<Vec<u8>>, Error> {
+    match arg {
+        Value::Bytevector(bytes) => Ok(bytes),
+        _ => Err(Error::wrong_type(proc, "bytevector", arg)),
+    }
+}
+
+fn byte_arg(proc: &str, arg: &Value) -> Result<u8, Error> {
+    match arg {
+        Value::Integer(n @ 0..=255) => Ok(*n as u8),
+        _ => Err(Error::wrong_type(proc, "byte", arg)),
+    }
+}
+
+/// Reads the optional `start` and `end` arguments selecting part of a sequence of length `len`,
+/// which default to the whole sequence.
+fn range_args(proc: &str, args: &[Value], len: usize) -> Result<(usize, usize), Error> {
+    let start = match args.first() {
+        Some(start) => index_arg(proc, start)?,
+        None => 0,
+    };
+    let end = match args.get(1) {
+        Some(end) => index_arg(proc, end)?,
+        None => len,
+    };
+    if end > len {
+        return Err(Error::wrong_type(proc, "index in range", &args[1]));
+    }
+    if start > end {
+        return Err(Error::wrong_type(proc, "index in range", &args[0]));
+    }
+    Ok((start, end))
+}
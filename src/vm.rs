@@ -0,0 +1,311 @@
+//! A stack machine running the bytecode produced by [`crate::compiler`].
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::compiler::{Code, Op};
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::eval::{self, Step};
+use crate::gc;
+use crate::lists::{list_arg, spread_apply_args, vec_to_list};
+use crate::types::Value;
+
+/// The variables of one activation of compiled code, addressed by slot. Slots hold `None` until
+/// the variable is assigned.
+pub struct Frame {
+    pub(crate) slots: RefCell<Vec<Option<Value>>>,
+    pub(crate) parent: Option<Rc<Frame>>,
+}
+
+impl Frame {
+    fn new(slots: Vec<Option<Value>>, parent: Option<Rc<Frame>>) -> Rc<Frame> {
+        let frame = Rc::new(Frame {
+            slots: RefCell::new(slots),
+            parent,
+        });
+        gc::track(&frame);
+        frame
+    }
+
+    /// Walks up `depth` frames.
+    fn ancestor(self: &Rc<Frame>, depth: u32) -> &Rc<Frame> {
+        let mut frame = self;
+        for _ in 0..depth {
+            frame = frame.parent.as_ref().unwrap();
+        }
+        frame
+    }
+}
+
+/// A compiled procedure, closing over the frame it was created in.
+pub struct Closure {
+    pub code: Rc<Code>,
+    pub frame: Rc<Frame>,
+    /// The environment the code's global variables are looked up in.
+    pub globals: Rc<Environment>,
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Closure")
+            .field("name", &self.code.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The state of a call in progress.
+struct Activation {
+    code: Rc<Code>,
+    pc: usize,
+    frame: Rc<Frame>,
+    globals: Rc<Environment>,
+    /// The height of the stack when the call started.
+    base: usize,
+}
+
+/// Runs the code of a top-level expression, looking up global variables in `globals`.
+pub fn run(code: Rc<Code>, globals: Rc<Environment>) -> Result<Value, Error> {
+    let frame = Frame::new(vec![None; code.slots], None);
+    let step = execute(Activation {
+        code,
+        pc: 0,
+        frame,
+        globals,
+        base: 0,
+    })?;
+    eval::finish(step)
+}
+
+/// Calls a compiled procedure with already evaluated arguments. A tail call it ends with to a
+/// procedure that isn't compiled is left to the caller, like the evaluator leaves its own.
+pub(crate) fn call(closure: &Closure, args: Vec<Value>) -> Result<Step, Error> {
+    execute(enter(closure, args, 0)?)
+}
+
+/// Makes the frame for a call to `closure`, binding its parameters to `args`.
+fn enter(closure: &Closure, mut args: Vec<Value>, base: usize) -> Result<Activation, Error> {
+    let code = &closure.code;
+    code.arity().check(code.display_name(), args.len())?;
+    if code.rest {
+        let rest = args
+            .drain(code.params..)
+            .rev()
+            .fold(Value::Null, |list, arg| Value::cons(arg, list));
+        args.push(rest);
+    }
+    let mut slots: Vec<Option<Value>> = args.into_iter().map(Some).collect();
+    slots.resize(code.slots, None);
+    Ok(Activation {
+        code: code.clone(),
+        pc: 0,
+        frame: Frame::new(slots, Some(closure.frame.clone())),
+        globals: closure.globals.clone(),
+        base,
+    })
+}
+
+fn execute(activation: Activation) -> Result<Step, Error> {
+    let mut stack = Vec::new();
+    let mut calls = Vec::new();
+    let mut current = activation;
+    loop {
+        match step(&mut current, &mut stack, &mut calls) {
+            Ok(Some(step)) => return Ok(step),
+            Ok(None) => (),
+            Err(e) => {
+                // The instruction that failed is the one before the program counter
+                return Err(match &current.code.spans[current.pc - 1] {
+                    Some(span) => e.with_span(span),
+                    None => e,
+                });
+            }
+        }
+    }
+}
+
+/// Runs instructions until the outermost call returns, which gives its result, or until a call
+/// or return switches to another activation. A tail call from the outermost call to a procedure
+/// that isn't compiled is given back rather than made, so that it doesn't nest on the Rust stack.
+fn step(
+    current: &mut Activation,
+    stack: &mut Vec<Value>,
+    calls: &mut Vec<Activation>,
+) -> Result<Option<Step>, Error> {
+    loop {
+        let op = current.code.ops[current.pc];
+        current.pc += 1;
+        match op {
+            Op::Const(index) => stack.push(current.code.constants[index as usize].clone()),
+            Op::Quote(index) => stack.push(current.code.literals[index as usize].to_value()),
+            Op::Local(depth, slot) => {
+                let frame = current.frame.ancestor(depth);
+                let value = frame.slots.borrow()[slot as usize].clone();
+                stack.push(value.unwrap_or(Value::Null));
+            }
+            Op::CheckedLocal(depth, slot, name) => {
+                let frame = current.frame.ancestor(depth);
+                let value = frame.slots.borrow()[slot as usize].clone();
+                match value {
+                    Some(value) => stack.push(value),
+                    None => return Err(Error::unbound(&current.code.names[name as usize])),
+                }
+            }
+            Op::SetLocal(depth, slot) => {
+                let frame = current.frame.ancestor(depth);
+                let value = stack.last().unwrap().clone();
+                frame.slots.borrow_mut()[slot as usize] = Some(value);
+            }
+            Op::Global(name) => {
+                let name = &current.code.names[name as usize];
+                match current.globals.get_value(name) {
+                    Some(value) => stack.push(value),
+                    None => return Err(Error::unbound(name)),
+                }
+            }
+            Op::SetGlobal(name) => {
+                let name = &current.code.names[name as usize];
+                if !current
+                    .globals
+                    .set_value(name, stack.last().unwrap().clone())
+                {
+                    return Err(Error::unbound(name));
+                }
+            }
+            Op::DefineGlobal(name) => {
+                let name = &current.code.names[name as usize];
+                current
+                    .globals
+                    .add_value(name, stack.last().unwrap().clone());
+            }
+            Op::Pop => {
+                stack.pop();
+            }
+            Op::Jump(to) => current.pc = to as usize,
+            Op::JumpIfFalse(to) => {
+                if !stack.pop().unwrap().is_true() {
+                    current.pc = to as usize;
+                }
+            }
+            Op::JumpIfFalseOrPop(to) => {
+                if stack.last().unwrap().is_true() {
+                    stack.pop();
+                } else {
+                    current.pc = to as usize;
+                }
+            }
+            Op::JumpIfTrueOrPop(to) => {
+                if stack.last().unwrap().is_true() {
+                    current.pc = to as usize;
+                } else {
+                    stack.pop();
+                }
+            }
+            Op::JumpUnlessMemv(data, to) => {
+                let key = stack.pop().unwrap();
+                let mut data = current.code.constants[data as usize].clone();
+                let mut found = false;
+                while let Value::Pair(pair) = data {
+                    if pair.car().is_eqv(&key) {
+                        found = true;
+                        break;
+                    }
+                    data = pair.cdr();
+                }
+                if !found {
+                    current.pc = to as usize;
+                }
+            }
+            Op::Cons => {
+                let car = stack.pop().unwrap();
+                let cdr = stack.pop().unwrap();
+                stack.push(Value::cons(car, cdr));
+            }
+            Op::Splice => {
+                let list = stack.pop().unwrap();
+                let tail = stack.pop().unwrap();
+                let items = list_arg("unquote-splicing", &list)?;
+                stack.push(vec_to_list(items, tail));
+            }
+            Op::ListToVector => {
+                let mut list = stack.pop().unwrap();
+                let mut items = Vec::new();
+                while let Value::Pair(pair) = list {
+                    items.push(pair.car());
+                    list = pair.cdr();
+                }
+                stack.push(Value::vector(items));
+            }
+            Op::Closure(index) => {
+                let closure = Rc::new(Closure {
+                    code: current.code.codes[index as usize].clone(),
+                    frame: current.frame.clone(),
+                    globals: current.globals.clone(),
+                });
+                gc::track(&closure);
+                stack.push(Value::Closure(closure));
+            }
+            Op::Call(argc) | Op::TailCall(argc) => {
+                let tail = matches!(op, Op::TailCall(_));
+                let mut args = stack.split_off(stack.len() - argc as usize);
+                let mut proc = stack.pop().unwrap();
+                while let Value::Primitive(primitive) = &proc {
+                    if !primitive.is_apply() {
+                        break;
+                    }
+                    (proc, args) = spread_apply_args(args)?;
+                }
+                let closure = match proc {
+                    Value::Closure(closure) => closure,
+                    proc if tail && calls.is_empty() => {
+                        let span = current.code.spans[current.pc - 1].clone();
+                        return Ok(Some(Step::Apply(proc, args, span)));
+                    }
+                    // Other procedures return right away
+                    proc => {
+                        let value = match proc {
+                            Value::Primitive(primitive) => primitive.call(args)?,
+                            proc => eval::apply(&proc, args)?,
+                        };
+                        stack.push(value);
+                        if tail {
+                            return Ok(ret(current, stack, calls));
+                        }
+                        continue;
+                    }
+                };
+                if tail {
+                    *current = enter(&closure, args, current.base)?;
+                } else {
+                    if calls.len() >= eval::MAX_DEPTH {
+                        return Err(Error::recursion_too_deep());
+                    }
+                    let callee = enter(&closure, args, stack.len())?;
+                    calls.push(std::mem::replace(current, callee));
+                }
+                return Ok(None);
+            }
+            Op::Return => return Ok(ret(current, stack, calls)),
+        }
+    }
+}
+
+/// Returns the value on top of the stack from the current call, giving it back if that was the
+/// outermost one.
+fn ret(
+    current: &mut Activation,
+    stack: &mut Vec<Value>,
+    calls: &mut Vec<Activation>,
+) -> Option<Step> {
+    let value = stack.pop().unwrap();
+    stack.truncate(current.base);
+    match calls.pop() {
+        Some(caller) => {
+            *current = caller;
+            stack.push(value);
+            None
+        }
+        None => Some(Step::Done(value)),
+    }
+}
@@ -0,0 +1,16 @@
+use rscheme::Interpreter;
+
+/// Runs `program` with both the evaluator and the virtual machine, in fresh interpreters, and
+/// returns what they printed or the error they raised once checked to be the same.
+pub fn both(program: &str) -> Result<String, String> {
+    let eval = Interpreter::new()
+        .eval_str(program)
+        .map(|value| value.to_string())
+        .map_err(|e| e.to_string());
+    let vm = Interpreter::new()
+        .run_str(program)
+        .map(|value| value.to_string())
+        .map_err(|e| e.to_string());
+    assert_eq!(eval, vm, "eval and the VM disagree on {}", program);
+    eval
+}
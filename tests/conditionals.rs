@@ -0,0 +1,55 @@
+mod common;
+
+use common::both;
+
+#[test]
+fn and_and_or_return_the_deciding_value() {
+    assert_eq!(
+        both("(list (and 1 2 3) (and) (and 1 #f 3) (or #f 4) (or) (or #f #f))").unwrap(),
+        "(3 #t #f 4 #f #f)"
+    );
+    assert_eq!(
+        both("(define n 0) (or 1 (set! n 1)) (and #f (set! n 2)) n").unwrap(),
+        "0"
+    );
+}
+
+#[test]
+fn and_and_or_call_their_last_expression_in_tail_position() {
+    let program = "(define (all n) (and #t (if (= n 0) 'done (all (- n 1)))))
+                   (define (any n) (or (= n 0) (any (- n 1))))
+                   (list (all 100000) (any 100000))";
+    assert_eq!(both(program).unwrap(), "(done #t)");
+}
+
+#[test]
+fn cond_passes_the_test_value_to_arrow_clauses() {
+    let program = "(define (lookup k)
+                     (cond ((assv k '((1 . a) (2 . b))) => cdr)
+                           (else 'none)))
+                   (list (lookup 2) (lookup 3) (cond ((+ 1 2))))";
+    assert_eq!(both(program).unwrap(), "(b none 3)");
+}
+
+#[test]
+fn case_matches_data_and_supports_arrow_clauses() {
+    let program = "(define (classify n)
+                     (case n
+                       ((2 3 5 7) 'prime)
+                       ((1) => (lambda (x) (list x 'unit)))
+                       (else => (lambda (x) (* x 2)))))
+                   (list (classify 3) (classify 1) (classify 10))";
+    assert_eq!(both(program).unwrap(), "(prime (1 unit) 20)");
+}
+
+#[test]
+fn do_steps_only_the_variables_with_step_expressions() {
+    assert_eq!(
+        both("(do ((i 0 (+ i 1)) (acc '() (cons i acc))) ((= i 4) acc))").unwrap(),
+        "(3 2 1 0)"
+    );
+    let program = "(let ((v (make-vector 3)))
+                     (do ((i 0 (+ i 1)) (fixed 'x)) ((= i 3) (list fixed v))
+                       (vector-set! v i (* i i))))";
+    assert_eq!(both(program).unwrap(), "(x #(0 1 4))");
+}
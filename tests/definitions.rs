@@ -0,0 +1,60 @@
+use rscheme::types::Value;
+use rscheme::Interpreter;
+
+#[test]
+fn internal_definitions_are_local_to_the_procedure() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define (f x)
+               (define (double y) (* 2 y))
+               (define z (double x))
+               (+ z 1))",
+        )
+        .unwrap();
+    assert_eq!(interpreter.eval_str("(f 4)").unwrap(), Value::Integer(9));
+    assert!(interpreter.eval_str("double").is_err());
+}
+
+#[test]
+fn failed_top_level_definitions_leave_the_name_unbound() {
+    let interpreter = Interpreter::new();
+    for program in [
+        "(begin (define a (car '())))",
+        "(when #t (define a (car '())))",
+        "(cond (#t (define a (car '()))))",
+    ] {
+        assert!(interpreter.eval_str(program).is_err());
+        assert!(interpreter.eval_str("(set! a 1)").is_err(), "{}", program);
+    }
+}
+
+#[test]
+fn definitions_in_a_clause_do_not_shadow_outer_bindings_before_they_run() {
+    let interpreter = Interpreter::new();
+    interpreter.eval_str("(define x 'outer)").unwrap();
+    let res = interpreter
+        .eval_str("(let () (when #t (define x x)) x)")
+        .unwrap();
+    assert_eq!(res.to_string(), "outer");
+}
+
+#[test]
+fn procedures_and_macros_print_with_their_names() {
+    let interpreter = Interpreter::new();
+    let res = interpreter
+        .eval_str(
+            "(define (f) 1)
+             (define-syntax m (syntax-rules () ((_) 1)))
+             (list car f (lambda (x) x) (case-lambda ((x) x)) m)",
+        )
+        .unwrap();
+    assert_eq!(
+        res.to_string(),
+        "(#<procedure car> #<procedure f> #<procedure> #<procedure> #<macro m>)"
+    );
+    let res = interpreter
+        .run_str("(define (g) 1) (list g (lambda () 1))")
+        .unwrap();
+    assert_eq!(res.to_string(), "(#<procedure g> #<procedure>)");
+}
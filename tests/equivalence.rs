@@ -0,0 +1,58 @@
+use rscheme::Interpreter;
+
+fn eval(program: &str) -> String {
+    Interpreter::new().eval_str(program).unwrap().to_string()
+}
+
+#[test]
+fn numbers_are_eqv_when_they_have_the_same_value_and_exactness() {
+    assert_eq!(
+        eval("(list (eq? 2 2) (eqv? 2 2) (eqv? 2 2.0) (eqv? 1/2 1/2) (equal? 2 2.0))"),
+        "(#t #t #f #t #f)"
+    );
+    assert_eq!(
+        eval("(eqv? 100000000000000000000 100000000000000000000)"),
+        "#t"
+    );
+    assert_eq!(eval("(list (eqv? 0.0 -0.0) (eqv? 0.0 0.0))"), "(#f #t)");
+}
+
+#[test]
+fn compound_data_is_only_equal_when_its_contents_are() {
+    assert_eq!(
+        eval("(list (equal? \"ab\" \"ab\") (equal? \"ab\" \"abc\") (eq? '() '()))"),
+        "(#t #f #t)"
+    );
+    assert_eq!(
+        eval(
+            "(define v (vector 1 (list 2)))
+             (list (eq? v v) (eqv? v (vector 1 (list 2))) (equal? v (vector 1 (list 2))))"
+        ),
+        "(#t #f #t)"
+    );
+    assert_eq!(
+        eval("(list (equal? (list 1 (vector 2 \"x\")) (list 1 (vector 2 \"x\"))) (equal? #(1) #(2)))"),
+        "(#t #f)"
+    );
+}
+
+#[test]
+fn procedures_are_only_eqv_to_themselves() {
+    assert_eq!(
+        eval(
+            "(define (f) 1)
+             (list (eq? car car) (eqv? f f) (equal? f f)
+                   (eqv? (lambda () 1) (lambda () 1)) (equal? car cdr))"
+        ),
+        "(#t #t #t #f #f)"
+    );
+}
+
+#[test]
+fn member_and_assoc_treat_any_non_false_result_as_a_match() {
+    assert_eq!(
+        eval("(member 2 '(1 2 3) (lambda (x y) (and (= x y) 'yes)))"),
+        "(2 3)"
+    );
+    assert_eq!(eval("(assoc 2.0 '((1 . a) (2 . b)) =)"), "(2 . b)");
+}
@@ -0,0 +1,118 @@
+use rscheme::types::Value;
+use rscheme::Interpreter;
+
+/// Returns the entry `name` of `(gc-statistics)`.
+fn statistic(interpreter: &Interpreter, name: &str) -> i64 {
+    let program = format!("(cdr (assq '{} (gc-statistics)))", name);
+    match interpreter.eval_str(&program).unwrap() {
+        Value::Integer(n) => n,
+        value => panic!("{} is not a count", value),
+    }
+}
+
+#[test]
+fn closure_environment_cycles_are_reclaimed() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define (make-cycle) (define (self) self) self)
+             (define (repeat n) (if (> n 0) (begin (make-cycle) (repeat (- n 1)))))
+             (repeat 1000)",
+        )
+        .unwrap();
+    let freed = interpreter.eval_str("(gc)").unwrap();
+    assert!(matches!(freed, Value::Integer(n) if n >= 2000), "{}", freed);
+    assert!(statistic(&interpreter, "reclaimed") >= 2000);
+    assert_eq!(statistic(&interpreter, "collections"), 1);
+}
+
+#[test]
+fn set_cdr_cycles_are_reclaimed() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define (make-ring) (let ((ring (list 1 2 3))) (set-cdr! (cdr (cdr ring)) ring) #t))
+             (define (repeat n) (if (> n 0) (begin (make-ring) (repeat (- n 1)))))
+             (repeat 1000)
+             (gc)",
+        )
+        .unwrap();
+    assert!(statistic(&interpreter, "reclaimed") >= 3000);
+    let live = statistic(&interpreter, "live");
+    interpreter.eval_str("(repeat 1000) (gc)").unwrap();
+    assert_eq!(statistic(&interpreter, "live"), live);
+}
+
+#[test]
+fn reachable_cycles_survive_a_collection() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define ring (list 1 2 3))
+             (set-cdr! (cdr (cdr ring)) ring)
+             (define (make-cycle) (define (self) self) self)
+             (define self (make-cycle))
+             (define v (vector 1 2))
+             (vector-set! v 1 v)
+             (gc)",
+        )
+        .unwrap();
+    let res = interpreter
+        .eval_str("(list (car (cdr (cdr (cdr ring)))) (eq? (self) self) (eq? (vector-ref v 1) v))")
+        .unwrap();
+    assert_eq!(res.to_string(), "(1 #t #t)");
+}
+
+#[test]
+fn cycles_in_active_frames_survive_a_collection_mid_recursion() {
+    let program = "(define (deep n)
+                     (let ((ring (list n n n)))
+                       (set-cdr! (cdr (cdr ring)) ring)
+                       (if (= n 0)
+                           (begin (gc) 0)
+                           (+ (car (cdr (cdr (cdr ring)))) (deep (- n 1))))))
+                   (deep 100)";
+    let interpreter = Interpreter::new();
+    assert_eq!(interpreter.eval_str(program).unwrap(), Value::Integer(5050));
+    assert_eq!(statistic(&interpreter, "collections"), 1);
+    let interpreter = Interpreter::new();
+    assert_eq!(interpreter.run_str(program).unwrap(), Value::Integer(5050));
+}
+
+#[test]
+fn cycles_in_active_frames_survive_an_automatic_collection() {
+    // Garbage that isn't cyclic is freed right away, so only cycles make a collection due
+    let program = "(define (make-ring)
+                     (let ((ring (list 1 2 3))) (set-cdr! (cdr (cdr ring)) ring)))
+                   (define (garbage n) (if (> n 0) (begin (make-ring) (garbage (- n 1)))))
+                   (define (deep n)
+                     (let ((ring (list n n n)))
+                       (set-cdr! (cdr (cdr ring)) ring)
+                       (if (= n 0)
+                           (begin (garbage 50000) 0)
+                           (+ (car (cdr (cdr (cdr ring)))) (deep (- n 1))))))
+                   (deep 100)";
+    for run_with_vm in [false, true] {
+        let interpreter = Interpreter::new();
+        let res = if run_with_vm {
+            interpreter.run_str(program)
+        } else {
+            interpreter.eval_str(program)
+        };
+        assert_eq!(res.unwrap(), Value::Integer(5050));
+        assert!(statistic(&interpreter, "collections") >= 1);
+    }
+}
+
+#[test]
+fn garbage_without_cycles_does_not_make_collections_due() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define (garbage n) (if (> n 0) (begin (list 1 2 3 4) (garbage (- n 1)))))
+             (garbage 100000)",
+        )
+        .unwrap();
+    assert!(statistic(&interpreter, "allocated") > 400000);
+    assert_eq!(statistic(&interpreter, "collections"), 0);
+}
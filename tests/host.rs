@@ -0,0 +1,95 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use rscheme::error::Error;
+use rscheme::types::{Arity, Value};
+use rscheme::Interpreter;
+
+#[test]
+fn rust_closures_can_be_called_from_scheme() {
+    let interpreter = Interpreter::new();
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    interpreter.define_primitive("add-offset", Arity::Between(1, 2), move |args| {
+        counter.set(counter.get() + 1);
+        let offset = match args.get(1) {
+            Some(Value::Integer(n)) => *n,
+            Some(other) => return Err(Error::wrong_type("add-offset", "integer", other)),
+            None => 100,
+        };
+        match &args[0] {
+            Value::Integer(n) => Ok(Value::Integer(n + offset)),
+            other => Err(Error::wrong_type("add-offset", "integer", other)),
+        }
+    });
+    let res = interpreter
+        .eval_str("(list (add-offset 1) (add-offset 1 2) (map add-offset '(10 20)))")
+        .unwrap();
+    assert_eq!(res.to_string(), "(101 3 (110 120))");
+    assert_eq!(calls.get(), 4);
+    let res = interpreter.run_str("(add-offset 5 5)").unwrap();
+    assert_eq!(res, Value::Integer(10));
+}
+
+#[test]
+fn primitive_errors_are_reported_like_builtin_ones() {
+    let interpreter = Interpreter::new();
+    interpreter.define_primitive("double", Arity::Exactly(1), |args| match &args[0] {
+        Value::Integer(n) => Ok(Value::Integer(n * 2)),
+        other => Err(Error::wrong_type("double", "integer", other)),
+    });
+    let err = interpreter.eval_str("(double 1 2)").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "double: expected 1 argument(s), got 2 (at 1:1)"
+    );
+    let err = interpreter.eval_str("\n  (double 'a)").unwrap_err();
+    assert_eq!(err.to_string(), "double: expected integer, got a (at 2:3)");
+    let err = interpreter.run_str("(double \"x\")").unwrap_err();
+    assert_eq!(err.to_string(), "double: expected integer, got x (at 1:1)");
+}
+
+#[test]
+fn globals_defined_from_rust_are_visible_to_scheme() {
+    let interpreter = Interpreter::new();
+    interpreter.define_global("limit", Value::Integer(42));
+    interpreter.define_global("greeting", Value::String("hello".to_string()));
+    let res = interpreter
+        .eval_str("(list (+ limit 1) (string-length greeting))")
+        .unwrap();
+    assert_eq!(res.to_string(), "(43 5)");
+    interpreter.eval_str("(set! limit 7)").unwrap();
+    assert_eq!(interpreter.eval_str("limit").unwrap(), Value::Integer(7));
+}
+
+#[test]
+fn scheme_procedures_can_be_called_from_rust() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str("(define (hypot-squared a b) (+ (* a a) (* b b)))")
+        .unwrap();
+    interpreter
+        .run_str("(define (compiled-square x) (* x x))")
+        .unwrap();
+    let res = interpreter
+        .call_procedure("hypot-squared", vec![Value::Integer(3), Value::Integer(4)])
+        .unwrap();
+    assert_eq!(res, Value::Integer(25));
+    let res = interpreter
+        .call_procedure("compiled-square", vec![Value::Integer(9)])
+        .unwrap();
+    assert_eq!(res, Value::Integer(81));
+    let res = interpreter
+        .call_procedure("car", vec![Value::cons(Value::Integer(1), Value::Null)])
+        .unwrap();
+    assert_eq!(res, Value::Integer(1));
+    let err = interpreter
+        .call_procedure("hypot-squared", vec![Value::Integer(3)])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "hypot-squared: expected 2 argument(s), got 1"
+    );
+    let err = interpreter.call_procedure("missing", vec![]).unwrap_err();
+    assert_eq!(err.to_string(), "Unbound variable: missing");
+}
@@ -0,0 +1,28 @@
+mod common;
+
+use common::both;
+
+#[test]
+fn case_lambda_runs_the_first_clause_accepting_the_arguments() {
+    let program = "(define f
+                     (case-lambda
+                       ((x) (list 'one x))
+                       ((x y) (list 'two x y))
+                       ((x . rest) (list 'many x rest))))
+                   (list (f 1) (f 1 2) (f 1 2 3))";
+    assert_eq!(both(program).unwrap(), "((one 1) (two 1 2) (many 1 (2 3)))");
+}
+
+#[test]
+fn case_lambda_without_a_matching_clause_is_an_arity_error() {
+    let program = "(define g (case-lambda ((x) x) ((x y) y)))
+                   (g 1 2 3)";
+    assert_eq!(
+        both(program).unwrap_err(),
+        "#<procedure>: expected between 1 and 2 argument(s), got 3 (at 2:20)"
+    );
+    assert_eq!(
+        both("((case-lambda ((x y . z) x)) 1)").unwrap_err(),
+        "#<procedure>: expected at least 2 argument(s), got 1 (at 1:1)"
+    );
+}
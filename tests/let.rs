@@ -0,0 +1,65 @@
+mod common;
+
+use common::both;
+
+#[test]
+fn let_evaluates_every_init_in_the_outer_scope() {
+    let program = "(define x 1)
+                   (let ((x 2) (y x)) (list x y))";
+    assert_eq!(both(program).unwrap(), "(2 1)");
+    assert_eq!(both("(let () 5)").unwrap(), "5");
+}
+
+#[test]
+fn let_star_sees_the_bindings_before_it() {
+    assert_eq!(
+        both("(let* ((x 1) (y (+ x 1)) (z (* y 10))) (list x y z))").unwrap(),
+        "(1 2 20)"
+    );
+    assert_eq!(both("(let* ((x 1) (x (+ x 1))) x)").unwrap(), "2");
+}
+
+#[test]
+fn letrec_bindings_can_refer_to_each_other() {
+    let program = "(letrec ((ev? (lambda (n) (if (= n 0) #t (od? (- n 1)))))
+                            (od? (lambda (n) (if (= n 0) #f (ev? (- n 1))))))
+                     (list (ev? 10) (od? 10)))";
+    assert_eq!(both(program).unwrap(), "(#t #f)");
+}
+
+#[test]
+fn named_let_binds_a_loop_procedure_in_its_body_only() {
+    let program = "(define (loop) 'outer)
+                   (list (let loop ((i 0) (acc '()))
+                           (if (= i 3) acc (loop (+ i 1) (cons i acc))))
+                         (loop))";
+    assert_eq!(both(program).unwrap(), "((2 1 0) outer)");
+}
+
+#[test]
+fn binding_a_name_twice_is_a_syntax_error() {
+    assert_eq!(
+        both("(let ((x 1) (x 2)) x)").unwrap_err(),
+        "Bad syntax in let: duplicate binding of x (at 1:1)"
+    );
+    assert_eq!(
+        both("(let loop ((i 0) (i 1)) i)").unwrap_err(),
+        "Bad syntax in let: duplicate binding of i (at 1:1)"
+    );
+    assert_eq!(
+        both("(letrec ((a 1) (a 2)) a)").unwrap_err(),
+        "Bad syntax in letrec: duplicate binding of a (at 1:1)"
+    );
+    assert_eq!(
+        both("(lambda (x x) x)").unwrap_err(),
+        "Bad syntax in lambda: duplicate binding of x (at 1:1)"
+    );
+    assert_eq!(
+        both("(define (f a . a) a)").unwrap_err(),
+        "Bad syntax in lambda: duplicate binding of a (at 1:1)"
+    );
+    assert_eq!(
+        both("(do ((i 0 (+ i 1)) (i 0)) (#t 1))").unwrap_err(),
+        "Bad syntax in do: duplicate binding of i (at 1:1)"
+    );
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::both;
+
+#[test]
+fn filter_keeps_the_elements_satisfying_the_predicate() {
+    assert_eq!(both("(filter odd? '(1 2 3 4 5))").unwrap(), "(1 3 5)");
+    assert_eq!(both("(filter odd? '())").unwrap(), "()");
+    assert_eq!(
+        both("(filter odd? '(1 2 . 3))").unwrap_err(),
+        "filter: expected list, got (1 2 . 3) (at 1:1)"
+    );
+}
+
+#[test]
+fn reduce_starts_from_the_first_element() {
+    assert_eq!(both("(reduce + 0 '(1 2 3 4))").unwrap(), "10");
+    assert_eq!(both("(reduce + 0 '())").unwrap(), "0");
+    assert_eq!(both("(reduce cons 'init '(1 2 3))").unwrap(), "(3 2 . 1)");
+}
+
+#[test]
+fn folds_combine_from_either_end() {
+    assert_eq!(
+        both("(fold-left cons '() '(1 2 3))").unwrap(),
+        "(((() . 1) . 2) . 3)"
+    );
+    assert_eq!(both("(fold-right cons '() '(1 2 3))").unwrap(), "(1 2 3)");
+    assert_eq!(
+        both("(list (fold-left list '() '(1 2) '(3 4)) (fold-right list 'end '(1 2) '(3 4)))")
+            .unwrap(),
+        "(((() 1 3) 2 4) (1 3 (2 4 end)))"
+    );
+}
+
+#[test]
+fn sort_is_stable() {
+    let program = "(sort '((b . 1) (a . 2) (c . 1) (d . 0) (e . 2))
+                         (lambda (x y) (< (cdr x) (cdr y))))";
+    assert_eq!(
+        both(program).unwrap(),
+        "((d . 0) (b . 1) (c . 1) (a . 2) (e . 2))"
+    );
+    assert_eq!(both("(sort (vector 3 1 2) <)").unwrap(), "#(1 2 3)");
+}
+
+#[test]
+fn assoc_and_member_accept_a_custom_predicate() {
+    assert_eq!(both("(assoc 2.0 '((1 . a) (2 . b)) =)").unwrap(), "(2 . b)");
+    assert_eq!(both("(assoc 5 '((1 . a)) =)").unwrap(), "#f");
+    assert_eq!(
+        both("(assoc \"b\" '((\"a\" . 1) (\"b\" . 2)))").unwrap(),
+        "(b . 2)"
+    );
+    assert_eq!(both("(member 2.0 '(1 2 3) =)").unwrap(), "(2 3)");
+}
+
+#[test]
+fn circular_lists_are_rejected() {
+    let program = "(define l (list 1 2 3))
+                   (set-cdr! (cdr (cdr l)) l)
+                   (length l)";
+    assert_eq!(
+        both(program).unwrap_err(),
+        "length: expected list, got (1 2 3 1 2 ...) (at 3:20)"
+    );
+    let program = "(define l (list 1))
+                   (set-cdr! l l)
+                   (map car l)";
+    assert_eq!(
+        both(program).unwrap_err(),
+        "map: expected list, got (1 ...) (at 3:20)"
+    );
+}
+
+#[test]
+fn apply_calls_the_procedure_in_tail_position() {
+    let program = "(define (loop n) (if (= n 0) 'ok (apply loop (list (- n 1)))))
+                   (loop 1000000)";
+    assert_eq!(both(program).unwrap(), "ok");
+    assert_eq!(
+        both("(list (apply + 1 2 '(3)) (apply apply + '((1 2))))").unwrap(),
+        "(6 3)"
+    );
+    assert_eq!(
+        both("(apply car '(1 2))").unwrap_err(),
+        "car: expected 1 argument(s), got 2 (at 1:1)"
+    );
+}
@@ -0,0 +1,168 @@
+use rscheme::Interpreter;
+
+fn eval(interpreter: &Interpreter, program: &str) -> String {
+    interpreter.eval_str(program).unwrap().to_string()
+}
+
+#[test]
+fn introduced_bindings_do_not_capture_user_variables() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define-syntax swap!
+               (syntax-rules ()
+                 ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp)))))",
+        )
+        .unwrap();
+    let res = eval(
+        &interpreter,
+        "(let ((tmp 1) (other 2)) (swap! tmp other) (list tmp other))",
+    );
+    assert_eq!(res, "(2 1)");
+}
+
+#[test]
+fn free_identifiers_refer_to_the_definition_environment() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define-syntax my-or
+               (syntax-rules ()
+                 ((_) #f)
+                 ((_ e) e)
+                 ((_ e r ...) (let ((t e)) (if t t (my-or r ...))))))",
+        )
+        .unwrap();
+    let res = eval(
+        &interpreter,
+        "(let ((if (lambda (a b c) 'user-if)) (t 5)) (my-or #f t))",
+    );
+    assert_eq!(res, "5");
+    interpreter.eval_str("(define (helper) 'global)").unwrap();
+    interpreter
+        .eval_str("(define-syntax call-helper (syntax-rules () ((_) (helper))))")
+        .unwrap();
+    let res = eval(
+        &interpreter,
+        "(let ((helper (lambda () 'local))) (call-helper))",
+    );
+    assert_eq!(res, "global");
+}
+
+#[test]
+fn nested_ellipses_follow_the_pattern_depth() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define-syntax my-let*
+               (syntax-rules ()
+                 ((_ () body ...) (let () body ...))
+                 ((_ ((x v) rest ...) body ...) (let ((x v)) (my-let* (rest ...) body ...)))))
+             (define-syntax flatten
+               (syntax-rules ()
+                 ((_ (a b ...) ...) '(a ... b ... ...))))",
+        )
+        .unwrap();
+    assert_eq!(
+        eval(&interpreter, "(my-let* ((a 1) (b (+ a 1))) (* a b))"),
+        "2"
+    );
+    assert_eq!(
+        eval(&interpreter, "(flatten (1 2 3) (4) (5 6))"),
+        "(1 4 5 2 3 6)"
+    );
+}
+
+#[test]
+fn a_custom_ellipsis_can_be_declared() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define-syntax my-list
+               (syntax-rules ::: ()
+                 ((_ x :::) (list x :::))))",
+        )
+        .unwrap();
+    assert_eq!(eval(&interpreter, "(my-list 1 2 3)"), "(1 2 3)");
+}
+
+#[test]
+fn an_escaped_ellipsis_is_inserted_literally() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define-syntax quote-ellipsis
+               (syntax-rules ()
+                 ((_ x) '(x (... ...)))))",
+        )
+        .unwrap();
+    assert_eq!(eval(&interpreter, "(quote-ellipsis a)"), "(a ...)");
+}
+
+#[test]
+fn literals_only_match_themselves() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define-syntax arrow
+               (syntax-rules (=>)
+                 ((_ a => b) (list 'arrow a b))
+                 ((_ a b c) (list 'plain a b c))))",
+        )
+        .unwrap();
+    assert_eq!(eval(&interpreter, "(arrow 1 => 2)"), "(arrow 1 2)");
+    assert_eq!(eval(&interpreter, "(arrow 1 2 3)"), "(plain 1 2 3)");
+    assert!(interpreter.eval_str("(arrow 1 2)").is_err());
+}
+
+#[test]
+fn let_syntax_macros_are_local() {
+    let interpreter = Interpreter::new();
+    let res = eval(
+        &interpreter,
+        "(let-syntax ((twice (syntax-rules () ((_ e) (begin e e)))))
+           (let ((n 0))
+             (twice (set! n (+ n 1)))
+             n))",
+    );
+    assert_eq!(res, "2");
+    assert!(interpreter.eval_str("(twice 1)").is_err());
+}
+
+#[test]
+fn letrec_syntax_macros_can_use_themselves() {
+    let interpreter = Interpreter::new();
+    let res = eval(
+        &interpreter,
+        "(letrec-syntax ((my-and (syntax-rules ()
+                                   ((_) #t)
+                                   ((_ e) e)
+                                   ((_ e r ...) (if e (my-and r ...) #f)))))
+           (list (my-and 1 2 3) (my-and 1 #f 3)))",
+    );
+    assert_eq!(res, "(3 #f)");
+}
+
+#[test]
+fn binding_a_name_twice_is_a_syntax_error() {
+    let interpreter = Interpreter::new();
+    let err = interpreter
+        .eval_str("(define-syntax m (syntax-rules () ((_ x (y x)) (list x))))")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Bad syntax in syntax-rules: duplicate binding of x (at 1:1)"
+    );
+    let err = interpreter
+        .eval_str("(let-syntax ((a (syntax-rules () ((_) 1))) (a (syntax-rules () ((_) 2)))) (a))")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Bad syntax in let-syntax: duplicate binding of a (at 1:1)"
+    );
+    // Neither the keyword position, `_` nor literals bind anything
+    interpreter
+        .eval_str("(define-syntax m (syntax-rules (=>) ((m _ _ => =>) 'ok)))")
+        .unwrap();
+    assert_eq!(eval(&interpreter, "(m 1 2 => =>)"), "ok");
+}
@@ -0,0 +1,151 @@
+use rscheme::types::Value;
+use rscheme::Interpreter;
+
+fn eval(program: &str) -> String {
+    Interpreter::new().eval_str(program).unwrap().to_string()
+}
+
+#[test]
+fn fixnum_overflow_promotes_to_bignums() {
+    assert_eq!(eval("(+ 9223372036854775807 1)"), "9223372036854775808");
+    assert_eq!(eval("(- -9223372036854775808 1)"), "-9223372036854775809");
+    assert_eq!(eval("(* 4294967296 4294967296)"), "18446744073709551616");
+    assert_eq!(
+        eval("(define (fact n) (if (= n 0) 1 (* n (fact (- n 1))))) (fact 25)"),
+        "15511210043330985984000000"
+    );
+}
+
+#[test]
+fn bignums_that_fit_become_fixnums_again() {
+    let res = Interpreter::new()
+        .eval_str("(- (+ 9223372036854775807 10) 10)")
+        .unwrap();
+    assert_eq!(res, Value::Integer(9223372036854775807));
+}
+
+#[test]
+fn rationals_are_exact_and_normalized() {
+    assert_eq!(eval("(/ 1 3)"), "1/3");
+    assert_eq!(eval("(/ 6 4)"), "3/2");
+    assert_eq!(eval("(+ 1/3 1/6)"), "1/2");
+    assert_eq!(eval("(/ -4 -6)"), "2/3");
+    assert_eq!(
+        Interpreter::new().eval_str("(* 2/3 3/2)").unwrap(),
+        Value::Integer(1)
+    );
+    assert_eq!(
+        Interpreter::new().eval_str("(+ 1/2 1/2)").unwrap(),
+        Value::Integer(1)
+    );
+}
+
+#[test]
+fn exactness_conversions_round_trip() {
+    assert_eq!(eval("(exact 0.5)"), "1/2");
+    assert_eq!(eval("(inexact 1/4)"), "0.25");
+    assert_eq!(eval("(exact (inexact 3/8))"), "3/8");
+    assert_eq!(eval("(inexact (exact 2.5))"), "2.5");
+    assert_eq!(eval("(exact 4.0)"), "4");
+    assert_eq!(eval("(exact? (exact 1.5))"), "#t");
+    assert_eq!(eval("(inexact? (inexact 3))"), "#t");
+}
+
+#[test]
+fn exactness_prefixes_are_parsed() {
+    assert_eq!(eval("#e1.25"), "5/4");
+    assert_eq!(eval("#e10"), "10");
+    assert_eq!(eval("#i3/4"), "0.75");
+    assert_eq!(eval("#i5"), "5.0");
+    assert_eq!(eval("(exact? #e0.1)"), "#t");
+}
+
+#[test]
+fn round_goes_to_even_on_ties() {
+    assert_eq!(eval("(round 0.5)"), "0.0");
+    assert_eq!(eval("(round 1.5)"), "2.0");
+    assert_eq!(eval("(round 2.5)"), "2.0");
+    assert_eq!(eval("(round -2.5)"), "-2.0");
+    assert_eq!(eval("(round 5/2)"), "2");
+    assert_eq!(eval("(round 7/2)"), "4");
+    assert_eq!(eval("(round 7/3)"), "2");
+}
+
+#[test]
+fn huge_exact_powers_are_rejected() {
+    let interpreter = Interpreter::new();
+    let err = interpreter.eval_str("(expt 10 2000000000)").unwrap_err();
+    assert!(
+        err.to_string().starts_with("expt: result too large"),
+        "{}",
+        err
+    );
+    assert!(interpreter.eval_str("(expt 2 -2000000000)").is_err());
+    assert!(interpreter
+        .eval_str("(expt 2/3 10000000000000000000000)")
+        .is_err());
+}
+
+#[test]
+fn powers_are_exact_when_possible() {
+    assert_eq!(eval("(expt 2 100)"), "1267650600228229401496703205376");
+    assert_eq!(eval("(expt 2/3 3)"), "8/27");
+    assert_eq!(eval("(expt 2 -2)"), "1/4");
+    assert_eq!(eval("(expt -1 10000000000000000000001)"), "-1");
+    assert_eq!(eval("(expt 0 10000000000000000000000)"), "0");
+    assert_eq!(eval("(expt 10.0 2000000000)"), "+inf.0");
+}
+
+#[test]
+fn floats_print_so_they_read_back_as_the_same_float() {
+    let interpreter = Interpreter::new();
+    for n in [
+        1e16,
+        -1e16,
+        1.5e300,
+        123456789012345680000.0,
+        1e-20,
+        0.1,
+        -0.0,
+    ] {
+        let printed = Value::Float(n).to_string();
+        assert!(printed.contains(['.', 'e']), "{}", printed);
+        assert_eq!(
+            interpreter.eval_str(&printed).unwrap(),
+            Value::Float(n),
+            "{}",
+            printed
+        );
+    }
+    assert_eq!(eval("(* 1.0 (expt 10 20))"), "1e20");
+}
+
+#[test]
+fn only_exact_zero_divisors_are_an_error() {
+    assert_eq!(eval("(/ 1.0 0.0)"), "+inf.0");
+    assert_eq!(eval("(/ -1 0.0)"), "-inf.0");
+    assert_eq!(eval("(/ 0.0 0.0)"), "+nan.0");
+    assert_eq!(eval("(/ 0.0)"), "+inf.0");
+    let err = Interpreter::new().eval_str("(/ 1.0 0)").unwrap_err();
+    assert!(
+        err.to_string().starts_with("/: division by zero"),
+        "{}",
+        err
+    );
+    assert_eq!(eval("(- 0.0)"), "-0.0");
+    assert_eq!(eval("(- 5)"), "-5");
+}
+
+#[test]
+fn comparisons_chain_across_exact_and_inexact_numbers() {
+    assert_eq!(eval("(< 1 2.5 3)"), "#t");
+    assert_eq!(eval("(< 1 2 2)"), "#f");
+    assert_eq!(eval("(< 1/3 0.34 1)"), "#t");
+    assert_eq!(eval("(= 1 1.0 1)"), "#t");
+    assert_eq!(eval("(= 1/2 0.5)"), "#t");
+    assert_eq!(eval("(= 1 1.0 2)"), "#f");
+    assert_eq!(eval("(= 0.0 -0.0)"), "#t");
+    assert_eq!(eval("(= 9223372036854775808 9223372036854775808.0)"), "#t");
+    let err = Interpreter::new().eval_str("(< 1 2 'a)").unwrap_err();
+    assert_eq!(err.to_string(), "<: expected number, got a (at 1:1)");
+}
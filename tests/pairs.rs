@@ -0,0 +1,36 @@
+use rscheme::types::Value;
+use rscheme::Interpreter;
+
+#[test]
+fn dropping_a_long_list_does_not_overflow_the_stack() {
+    let mut list = Value::Null;
+    for n in 0..1_000_000 {
+        list = Value::cons(Value::Integer(n), list);
+    }
+    drop(list);
+}
+
+#[test]
+fn redefining_a_long_list_frees_it() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str("(define (build n acc) (if (= n 0) acc (build (- n 1) (cons n acc))))")
+        .unwrap();
+    interpreter
+        .eval_str("(define a (build 200000 '()))")
+        .unwrap();
+    interpreter.eval_str("(define c (list-copy a))").unwrap();
+    interpreter.eval_str("(define c 1)").unwrap();
+    interpreter.eval_str("(define a 1)").unwrap();
+    assert_eq!(interpreter.eval_str("(+ a c)").unwrap(), Value::Integer(2));
+}
+
+#[test]
+fn dropping_a_list_keeps_shared_tails() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str("(define tail (list 3 4)) (define l (cons 1 (cons 2 tail)))")
+        .unwrap();
+    interpreter.eval_str("(define l #f)").unwrap();
+    assert_eq!(interpreter.eval_str("tail").unwrap().to_string(), "(3 4)");
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::both;
+
+#[test]
+fn unquote_evaluates_only_at_the_outermost_level() {
+    assert_eq!(
+        both("`(1 `(2 ,(3 ,(+ 1 2))))").unwrap(),
+        "(1 (quasiquote (2 (unquote (3 3)))))"
+    );
+    assert_eq!(both("``,,(+ 1 2)").unwrap(), "(quasiquote (unquote 3))");
+    assert_eq!(
+        both("`(1 `,@(a ,@(list 2 3)))").unwrap(),
+        "(1 (quasiquote (unquote-splicing (a 2 3))))"
+    );
+}
+
+#[test]
+fn unquote_splicing_works_in_vectors_and_before_dotted_tails() {
+    assert_eq!(both("`#(1 ,@(list 2 3))").unwrap(), "#(1 2 3)");
+    assert_eq!(both("`#(,@'() ,(+ 1 1))").unwrap(), "#(2)");
+    assert_eq!(both("`(1 . ,(+ 1 1))").unwrap(), "(1 . 2)");
+    assert_eq!(both("`(1 ,@(list 2 3) . 4)").unwrap(), "(1 2 3 . 4)");
+}
+
+#[test]
+fn unquote_splicing_outside_a_list_is_an_error() {
+    assert_eq!(
+        both("`,@(list 1)").unwrap_err(),
+        "Bad syntax in unquote-splicing: not in a list or vector (at 1:1)"
+    );
+    assert_eq!(
+        both("`(1 . ,@(list 2))").unwrap_err(),
+        "Bad syntax in unquote-splicing: not in a list or vector (at 1:1)"
+    );
+    assert_eq!(
+        both("`(1 ,@2)").unwrap_err(),
+        "unquote-splicing: expected list, got 2 (at 1:1)"
+    );
+}
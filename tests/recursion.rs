@@ -0,0 +1,18 @@
+mod common;
+
+use common::both;
+
+#[test]
+fn deep_non_tail_recursion_does_not_overflow_the_stack() {
+    let program = "(define (build n acc) (if (= n 0) acc (build (- n 1) (cons n acc))))
+                   (define (count l) (if (null? l) 0 (+ 1 (count (cdr l)))))
+                   (count (build 10000 '()))";
+    assert_eq!(both(program).unwrap(), "10000");
+}
+
+#[test]
+fn runaway_recursion_is_an_error() {
+    let program = "(define (f n) (+ 1 (f n)))
+                   (f 0)";
+    assert_eq!(both(program).unwrap_err(), "Recursion too deep (at 1:20)");
+}
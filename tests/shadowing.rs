@@ -0,0 +1,45 @@
+mod common;
+
+use common::both;
+use rscheme::types::Value;
+use rscheme::Interpreter;
+
+#[test]
+fn macros_shadow_special_forms() {
+    let program = "(define-syntax unless
+                     (syntax-rules () ((_ c body ...) (if c 'skipped (begin body ...)))))
+                   (unless #t 1)";
+    assert_eq!(both(program).unwrap(), "skipped");
+}
+
+#[test]
+fn parameters_shadow_special_forms() {
+    assert_eq!(
+        both("(define (f list when) (when 1)) (f 0 -)").unwrap(),
+        "-1"
+    );
+}
+
+#[test]
+fn local_variables_shadow_special_forms() {
+    assert_eq!(
+        both("(let ((if (lambda (a b c) 'shadowed))) (if #t 1 2))").unwrap(),
+        "shadowed"
+    );
+}
+
+#[test]
+fn global_variables_shadow_special_forms() {
+    let interpreter = Interpreter::new();
+    interpreter.eval_str("(define (do x) (* x 2))").unwrap();
+    assert_eq!(interpreter.eval_str("(do 21)").unwrap(), Value::Integer(42));
+    assert_eq!(interpreter.run_str("(do 21)").unwrap(), Value::Integer(42));
+}
+
+#[test]
+fn special_forms_are_used_outside_the_shadowing_scope() {
+    assert_eq!(
+        both("(define (f if) (if 1 2 3)) (if (f (lambda (a b c) #f)) 'yes 'no)").unwrap(),
+        "no"
+    );
+}
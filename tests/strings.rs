@@ -0,0 +1,66 @@
+use rscheme::Interpreter;
+
+fn eval(program: &str) -> Result<String, String> {
+    Interpreter::new()
+        .eval_str(program)
+        .map(|v| v.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn string_indices_are_bounds_checked() {
+    assert_eq!(eval("(string-ref \"héllo\" 1)").unwrap(), "#\\é");
+    assert_eq!(
+        eval("(string-ref \"abc\" 3)").unwrap_err(),
+        "string-ref: expected index in range, got 3 (at 1:1)"
+    );
+    assert_eq!(
+        eval("(string-ref \"abc\" -1)").unwrap_err(),
+        "string-ref: expected non-negative integer, got -1 (at 1:1)"
+    );
+}
+
+#[test]
+fn substring_takes_an_optional_end() {
+    assert_eq!(eval("(substring \"hello\" 1 3)").unwrap(), "el");
+    assert_eq!(eval("(substring \"hello\" 2)").unwrap(), "llo");
+    assert_eq!(eval("(substring \"hello\" 5)").unwrap(), "");
+    assert_eq!(
+        eval("(substring \"hello\" 3 2)").unwrap_err(),
+        "substring: expected index in range, got 3 (at 1:1)"
+    );
+    assert_eq!(
+        eval("(substring \"hello\" 0 9)").unwrap_err(),
+        "substring: expected index in range, got 9 (at 1:1)"
+    );
+}
+
+#[test]
+fn strings_convert_to_and_from_lists_of_characters() {
+    assert_eq!(
+        eval("(string->list \"héllo\")").unwrap(),
+        "(#\\h #\\é #\\l #\\l #\\o)"
+    );
+    assert_eq!(eval("(list->string (list #\\a #\\b))").unwrap(), "ab");
+    assert_eq!(eval("(list->string '())").unwrap(), "");
+    assert_eq!(
+        eval("(equal? (list->string (string->list \"a\\tb\")) \"a\\tb\")").unwrap(),
+        "#t"
+    );
+    assert_eq!(
+        eval("(list->string (list #\\a 1))").unwrap_err(),
+        "list->string: expected character, got 1 (at 1:1)"
+    );
+}
+
+#[test]
+fn only_decimal_digits_are_numeric_characters() {
+    assert_eq!(
+        eval("(map char-numeric? (list #\\0 #\\9 #\\a #\\½ #\\Ⅻ))").unwrap(),
+        "(#t #t #f #f #f)"
+    );
+    assert_eq!(
+        eval("(char-numeric? 1)").unwrap_err(),
+        "char-numeric?: expected character, got 1 (at 1:1)"
+    );
+}
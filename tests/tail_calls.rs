@@ -0,0 +1,25 @@
+use rscheme::types::Value;
+use rscheme::Interpreter;
+
+#[test]
+fn tail_recursive_loop_runs_in_constant_stack_space() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str("(define (count-up n acc) (if (= n 0) acc (count-up (- n 1) (+ acc 1))))")
+        .unwrap();
+    let res = interpreter.eval_str("(count-up 1000000 0)").unwrap();
+    assert_eq!(res, Value::Integer(1000000));
+}
+
+#[test]
+fn mutually_recursive_tail_calls_through_cond() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "(define (my-even? n) (cond ((= n 0) #t) (#t (my-odd? (- n 1)))))
+             (define (my-odd? n) (cond ((= n 0) #f) (#t (my-even? (- n 1)))))",
+        )
+        .unwrap();
+    let res = interpreter.eval_str("(my-even? 100001)").unwrap();
+    assert_eq!(res, Value::Boolean(false));
+}
@@ -0,0 +1,66 @@
+use rscheme::Interpreter;
+
+fn eval(program: &str) -> Result<String, String> {
+    Interpreter::new()
+        .eval_str(program)
+        .map(|v| v.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn vector_indices_are_bounds_checked() {
+    assert_eq!(eval("(vector-ref (vector 1 2) 1)").unwrap(), "2");
+    assert_eq!(
+        eval("(vector-ref (vector 1 2) 2)").unwrap_err(),
+        "vector-ref: expected index in range, got 2 (at 1:1)"
+    );
+    assert_eq!(
+        eval("(vector-ref (vector 1 2) 1.0)").unwrap_err(),
+        "vector-ref: expected non-negative integer, got 1.0 (at 1:1)"
+    );
+}
+
+#[test]
+fn vector_fill_replaces_every_element_in_place() {
+    let program = "(define v (make-vector 3 0))
+                   (define alias v)
+                   (vector-fill! v 7)
+                   alias";
+    assert_eq!(eval(program).unwrap(), "#(7 7 7)");
+    assert_eq!(
+        eval("(define v (vector)) (vector-fill! v 1) v").unwrap(),
+        "#()"
+    );
+}
+
+#[test]
+fn bytevector_elements_must_be_bytes() {
+    let program = "(define b (make-bytevector 2 0))
+                   (bytevector-u8-set! b 1 255)
+                   b";
+    assert_eq!(eval(program).unwrap(), "#u8(0 255)");
+    assert_eq!(
+        eval("(bytevector-u8-set! (make-bytevector 2 0) 0 256)").unwrap_err(),
+        "bytevector-u8-set!: expected byte, got 256 (at 1:1)"
+    );
+    assert_eq!(
+        eval("(bytevector-u8-set! (make-bytevector 2 0) 0 -1)").unwrap_err(),
+        "bytevector-u8-set!: expected byte, got -1 (at 1:1)"
+    );
+    assert_eq!(
+        eval("(bytevector-u8-set! (make-bytevector 2 0) 2 1)").unwrap_err(),
+        "bytevector-u8-set!: expected index in range, got 2 (at 1:1)"
+    );
+}
+
+#[test]
+fn bytevector_literals_read_and_print_the_same() {
+    assert_eq!(eval("#u8(1 2 255)").unwrap(), "#u8(1 2 255)");
+    assert_eq!(eval("#u8()").unwrap(), "#u8()");
+    assert_eq!(eval("(bytevector-u8-ref #u8(1 2 3) 2)").unwrap(), "3");
+    assert_eq!(eval("(bytevector-copy #u8(1 2 3) 1)").unwrap(), "#u8(2 3)");
+    assert_eq!(
+        eval("#u8(1 256)").unwrap_err(),
+        "Read error: bytevector elements must be bytes (at 1:7)"
+    );
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::both;
+use rscheme::types::Value;
+use rscheme::Interpreter;
+
+#[test]
+fn closures_capture_their_frame() {
+    let program = "(define (make-adder n) (lambda (x) (+ x n)))
+                   (define add5 (make-adder 5))
+                   (list (add5 1) ((make-adder 10) 1))";
+    assert_eq!(both(program).unwrap(), "(6 11)");
+}
+
+#[test]
+fn set_on_captured_locals_is_shared() {
+    let program = "(define (make-counter)
+                     (let ((n 0))
+                       (cons (lambda () (set! n (+ n 1)) n) (lambda () n))))
+                   (define c (make-counter))
+                   ((car c))
+                   ((car c))
+                   (list ((car c)) ((cdr c)))";
+    assert_eq!(both(program).unwrap(), "(3 3)");
+}
+
+#[test]
+fn named_let_loops() {
+    let program = "(let loop ((i 0) (acc '()))
+                     (if (= i 5) (reverse acc) (loop (+ i 1) (cons (* i i) acc))))";
+    assert_eq!(both(program).unwrap(), "(0 1 4 9 16)");
+}
+
+#[test]
+fn internal_definitions_are_mutually_recursive() {
+    let program = "(define (parity n)
+                     (define (ev? n) (if (= n 0) #t (od? (- n 1))))
+                     (define (od? n) (if (= n 0) #f (ev? (- n 1))))
+                     (if (ev? n) 'even 'odd))
+                   (list (parity 10) (parity 7))";
+    assert_eq!(both(program).unwrap(), "(even odd)");
+    let program = "(define (f) (define a b) (define b 1) a) (f)";
+    assert_eq!(both(program).unwrap_err(), "Unbound variable: b (at 1:13)");
+}
+
+#[test]
+fn rest_arguments_are_collected_in_a_list() {
+    let program = "(define (f a . rest) (list a rest))
+                   (define g (lambda args args))
+                   (list (f 1) (f 1 2 3) (g) (g 4 5))";
+    assert_eq!(both(program).unwrap(), "((1 ()) (1 (2 3)) () (4 5))");
+    assert_eq!(
+        both("(define (f a b . rest) a) (f 1)").unwrap_err(),
+        "f: expected at least 2 argument(s), got 1 (at 1:27)"
+    );
+}
+
+#[test]
+fn letrec_evaluates_every_init_before_binding() {
+    assert_eq!(
+        both("(letrec ((a 1) (b a)) b)").unwrap_err(),
+        "Unbound variable: a (at 1:1)"
+    );
+    assert_eq!(both("(letrec* ((a 1) (b a)) b)").unwrap(), "1");
+    let program = "(letrec ((ev? (lambda (n) (if (= n 0) #t (od? (- n 1)))))
+                            (od? (lambda (n) (if (= n 0) #f (ev? (- n 1))))))
+                     (ev? 100))";
+    assert_eq!(both(program).unwrap(), "#t");
+}
+
+#[test]
+fn quoted_literals_are_fresh_each_time() {
+    let program = "(define (f)
+                     (let ((l '(1 2)) (v #(1 2)))
+                       (let ((before (list (car l) (vector-ref v 0))))
+                         (set-car! l 'x)
+                         (vector-set! v 0 'y)
+                         (list before l v))))
+                   (f)
+                   (f)";
+    assert_eq!(both(program).unwrap(), "((1 1) (x 2) #(y 2))");
+}
+
+#[test]
+fn errors_point_at_the_failing_expression() {
+    assert_eq!(
+        both("(define (f x) (car x))\n(f 1)").unwrap_err(),
+        "car: expected pair, got 1 (at 1:15)"
+    );
+    assert_eq!(
+        both("(+ 1\n   (undefined-procedure 2))").unwrap_err(),
+        "Unbound variable: undefined-procedure (at 2:4)"
+    );
+    assert_eq!(
+        both("((lambda (x) x))").unwrap_err(),
+        "#<procedure>: expected 1 argument(s), got 0 (at 1:1)"
+    );
+}
+
+#[test]
+fn tail_calls_run_in_constant_space() {
+    let interpreter = Interpreter::new();
+    let res = interpreter
+        .run_str(
+            "(define (count-up n acc) (if (= n 0) acc (count-up (- n 1) (+ acc 1))))
+             (count-up 1000000 0)",
+        )
+        .unwrap();
+    assert_eq!(res, Value::Integer(1000000));
+}
+
+#[test]
+fn do_loops_give_each_iteration_its_own_variables() {
+    let program = "(do ((i 0 (+ i 1))
+                        (procs '() (cons (lambda () i) procs)))
+                       ((= i 3) (map (lambda (p) (p)) procs)))";
+    assert_eq!(both(program).unwrap(), "(2 1 0)");
+}
+
+#[test]
+fn case_and_cond_keep_their_values_for_receivers() {
+    let program = "(define (f x)
+                     (case (* x 2)
+                       ((2 4) => (lambda (y) (cond ((assv y '((2 . two))) => cdr) (else y))))
+                       (else => -)))
+                   (list (f 1) (f 2) (f 3))";
+    assert_eq!(both(program).unwrap(), "(two 4 -6)");
+}
+
+#[test]
+fn tail_calls_between_compiled_and_evaluated_code_run_in_constant_space() {
+    // `case-lambda` isn't compiled, so `g` is left to the evaluator while `f` runs on the VM
+    let program = "(define g
+                     (case-lambda
+                       ((n) (case n ((0) 'done) (else (f (- n 1)))))))
+                   (define (f n) (if (= n 0) 'done (g (- n 1))))
+                   (f 1000000)";
+    assert_eq!(both(program).unwrap(), "done");
+}
+
+#[test]
+fn macros_defined_after_their_use_are_expanded() {
+    let program = "(define (f) (mk 1))
+                   (define-syntax mk (syntax-rules () ((_ x) (list x))))
+                   (f)";
+    assert_eq!(both(program).unwrap(), "(1)");
+}